@@ -1,6 +1,9 @@
 use embedded_hal::digital::v2::{InputPin, OutputPin};
 
-use crate::util::safe_spi::{ChipSelect, SafeSpi};
+use crate::util::{
+    safe_spi::{ChipSelect, SafeSpi},
+    timer::Timer,
+};
 
 #[derive(Debug)]
 pub enum WifiNinaChipSelectError<CsPinError, BusyPinError> {
@@ -13,8 +16,8 @@ pub enum WifiNinaChipSelectError<CsPinError, BusyPinError> {
 // and only returns selected when it’s indictating that the device is
 // ready to listen.
 //
-// Its select method needs a timer in order to fail if the device isn’t
-// ready by a deadline.
+// Its select method needs a delay and a timer in order to fail if the device
+// isn’t ready by a deadline.
 pub struct WifiNinaChipSelect<S, CsPin: OutputPin, BusyPin: InputPin> {
     spi: core::marker::PhantomData<S>,
 
@@ -22,6 +25,9 @@ pub struct WifiNinaChipSelect<S, CsPin: OutputPin, BusyPin: InputPin> {
     busy: BusyPin,
 
     last_deselect_err: Option<WifiNinaChipSelectError<CsPin::Error, BusyPin::Error>>,
+
+    // Number of selects in a row that have failed with DeviceReadyTimeout.
+    consecutive_timeouts: u8,
 }
 
 impl<S, CsPin, BusyPin> WifiNinaChipSelect<S, CsPin, BusyPin>
@@ -34,14 +40,14 @@ where
         mut cs: CsPin,
         busy: BusyPin,
     ) -> Result<Self, WifiNinaChipSelectError<CsPin::Error, BusyPin::Error>> {
-        cs.set_high()
-            .map_err(|err| WifiNinaChipSelectError::CsPinError(err))?;
+        cs.set_high().map_err(WifiNinaChipSelectError::CsPinError)?;
 
         Ok(WifiNinaChipSelect {
             spi: core::marker::PhantomData,
             cs,
             busy,
             last_deselect_err: None,
+            consecutive_timeouts: 0,
         })
     }
 
@@ -49,25 +55,71 @@ where
         &'a mut self,
         spi: &'a mut S,
         delay: &mut impl embedded_hal::blocking::delay::DelayMs<u16>,
+        timer: &mut impl Timer,
     ) -> Result<SafeSpi<'a, S, Self>, WifiNinaChipSelectError<CsPin::Error, BusyPin::Error>> {
-        self.wait_for_busy(delay, 10_000, false)?;
+        match self.wait_until_selected(delay, timer) {
+            Ok(()) => self.consecutive_timeouts = 0,
+            Err(err) => {
+                if let WifiNinaChipSelectError::DeviceReadyTimeout = err {
+                    self.consecutive_timeouts = self.consecutive_timeouts.saturating_add(1);
+                }
+
+                return Err(err);
+            }
+        }
+
+        Ok(SafeSpi::new(spi, self))
+    }
+
+    pub fn consecutive_timeouts(&self) -> u8 {
+        self.consecutive_timeouts
+    }
+
+    pub fn clear_timeouts(&mut self) {
+        self.consecutive_timeouts = 0;
+    }
+
+    // Drives CS high and forgets any earlier failures, for starting over
+    // after the chip has been reset.
+    pub fn release(&mut self) -> Result<(), WifiNinaChipSelectError<CsPin::Error, BusyPin::Error>> {
+        self.last_deselect_err = None;
+        self.consecutive_timeouts = 0;
 
         self.cs
-            .set_low()
-            .map_err(|err| WifiNinaChipSelectError::CsPinError(err))?;
+            .set_high()
+            .map_err(WifiNinaChipSelectError::CsPinError)
+    }
 
-        self.wait_for_busy(delay, 1_000, true)?;
+    pub fn take_deselect_error(
+        &mut self,
+    ) -> Option<WifiNinaChipSelectError<CsPin::Error, BusyPin::Error>> {
+        self.last_deselect_err.take()
+    }
 
-        Ok(SafeSpi::new(spi, self))
+    fn wait_until_selected(
+        &mut self,
+        delay: &mut impl embedded_hal::blocking::delay::DelayMs<u16>,
+        timer: &mut impl Timer,
+    ) -> Result<(), WifiNinaChipSelectError<CsPin::Error, BusyPin::Error>> {
+        self.wait_for_busy(delay, timer, 10_000, false)?;
+
+        self.cs
+            .set_low()
+            .map_err(WifiNinaChipSelectError::CsPinError)?;
+
+        self.wait_for_busy(delay, timer, 1_000, true)
     }
 
     fn wait_for_busy(
         &mut self,
         delay: &mut impl embedded_hal::blocking::delay::DelayMs<u16>,
-        timeout: u16,
+        timer: &mut impl Timer,
+        timeout_ms: u16,
         val: bool,
     ) -> Result<(), WifiNinaChipSelectError<CsPin::Error, BusyPin::Error>> {
-        for attempt in 0..timeout {
+        timer.start(timeout_ms);
+
+        loop {
             match self.busy.is_high() {
                 Ok(b) => {
                     if b == val {
@@ -77,19 +129,11 @@ where
                 Err(err) => return Err(WifiNinaChipSelectError::BusyPinError(err)),
             }
             delay.delay_ms(1);
+
+            if timer.expired(1) {
+                return Err(WifiNinaChipSelectError::DeviceReadyTimeout);
+            }
         }
-        // for _ in timer.timeout_iter(timeout) {
-        //     match self.busy.is_high() {
-        //         Ok(b) => {
-        //             if b == val {
-        //                 return Ok(());
-        //             }
-        //         }
-        //         Err(err) => return Err(WifiNinaChipSelectError::BusyPinError(err)),
-        //     }
-        // }
-
-        Err(WifiNinaChipSelectError::DeviceReadyTimeout)
     }
 }
 
@@ -100,11 +144,12 @@ where
 {
     type Spi = S;
 
+    // Deselecting happens on drop, where there’s no one to return an error
+    // to, so a failure is kept until take_deselect_error. Later successes
+    // don’t clear it.
     fn deselect(&mut self) {
-        self.last_deselect_err = self
-            .cs
-            .set_high()
-            .map_err(|err| WifiNinaChipSelectError::CsPinError(err))
-            .err();
+        if let Err(err) = self.cs.set_high() {
+            self.last_deselect_err = Some(WifiNinaChipSelectError::CsPinError(err));
+        }
     }
 }
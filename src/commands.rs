@@ -1,5 +1,8 @@
+pub mod diagnostics;
+pub mod gpio;
 pub mod network;
 pub mod socket;
+pub mod tls;
 pub mod wifi;
 
 use core::fmt::Debug;
@@ -10,7 +13,12 @@ use embedded_hal::{
 };
 use nb::block;
 
-use crate::{util::spi_ext::SpiExt, Error, WifiNina};
+use crate::{
+    config::{Config, ParamLength},
+    metrics::Event,
+    util::spi_ext::SpiExt,
+    Error, Phase, WifiNina,
+};
 
 use self::socket::InvalidSocket;
 
@@ -29,11 +37,13 @@ pub enum NinaCommand {
     SetApNetwork = 0x18,
     SetApPassphrase = 0x19,
     SetDebug = 0x1A,
+    GetReasonCode = 0x1F,
 
     GetConnectionStatus = 0x20,
     GetIpAddress = 0x21,
     GetMacAddress = 0x22,
     GetCurrentSsid = 0x23,
+    GetCurrentBssid = 0x24,
     GetCurrentRssi = 0x25,
     GetCurrentEnct = 0x26,
     ScanNetworks = 0x27,
@@ -57,10 +67,19 @@ pub enum NinaCommand {
     GetHostByName = 0x35,
     StartScanNetworks = 0x36,
     GetFirmwareVersion = 0x37,
+    SendUdpData = 0x39,
+    GetRemoteData = 0x3A,
+    GetTime = 0x3B,
+    GetIdxBssid = 0x3C,
+    GetIdxChannel = 0x3D,
     Ping = 0x3E,
 
+    SetClientCert = 0x40,
+    SetCertKey = 0x41,
+
     SendDataTcp = 0x44,
     GetDatabufTcp = 0x45,
+    InsertDataBuf = 0x46,
 
     SetEnterpriseIdent = 0x4A,
     SetEnterpriseUsername = 0x4B,
@@ -76,9 +95,21 @@ pub enum NinaCommand {
     Error = 0xEF,
 }
 
-impl Into<u8> for NinaCommand {
-    fn into(self) -> u8 {
-        self as u8
+impl NinaCommand {
+    // Commands that move socket data around. These are the ones whose param
+    // length encoding has varied between firmware builds, so
+    // Config::data_param_length applies to them.
+    fn is_data_command(self) -> bool {
+        matches!(
+            self,
+            NinaCommand::SendDataTcp | NinaCommand::GetDatabufTcp | NinaCommand::InsertDataBuf
+        )
+    }
+}
+
+impl From<NinaCommand> for u8 {
+    fn from(cmd: NinaCommand) -> u8 {
+        cmd as u8
     }
 }
 
@@ -90,13 +121,13 @@ enum NinaResponse {
     Error = 255,
 }
 
-impl Into<u8> for NinaResponse {
-    fn into(self) -> u8 {
-        self as u8
+impl From<NinaResponse> for u8 {
+    fn from(resp: NinaResponse) -> u8 {
+        resp as u8
     }
 }
 
-impl<CsPin, BusyPin, Spi, SpiError, Delay> WifiNina<CsPin, BusyPin, Spi, Delay>
+impl<CsPin, BusyPin, Spi, SpiError, Delay, Timer> WifiNina<CsPin, BusyPin, Spi, Delay, Timer>
 where
     BusyPin: InputPin,
     CsPin: OutputPin,
@@ -104,130 +135,121 @@ where
         FullDuplex<u8, Error = SpiError> + embedded_hal::blocking::spi::Write<u8, Error = SpiError>,
     SpiError: Debug,
     //+ embedded_hal::blocking::spi::WriteIter<u8, Error = SpiError>,
-    Delay: embedded_hal::blocking::delay::DelayMs<u16>,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    Timer: crate::util::timer::Timer,
 {
     const REPLY_FLAG: u8 = 1 << 7;
 
-    // Static method because it needs to be called while chip_select is mutably
-    // borrowed
-    fn wait_for_response_start(spi: &mut Spi, delay: &mut Delay) -> Result<(), Error<SpiError>> {
-        for attempt in 0..100 {
-            let byte = spi.transfer_byte().map_err(Error::spi)?;
-
-            if byte == NinaCommand::Start.into() {
-                return Ok(());
-            } else if byte == NinaCommand::Error.into() {
-                return Err(Error::ErrorResponse);
-            }
-            delay.delay_ms(1);
-        }
-
-        // for _ in timer.timeout_iter(100.ms()) {
-        //     let byte = spi.transfer_byte().map_err(Error::spi)?;
-
-        //     if byte == NinaCommand::Start.into() {
-        //         return Ok(());
-        //     } else if byte == NinaCommand::Error.into() {
-        //         return Err(Error::ErrorResponse);
-        //     }
-        // }
+    // Filler for commands that take a param but don’t look at it.
+    pub(crate) const DUMMY_DATA: u8 = 0xFF;
 
-        Err(Error::ResponseTimeout)
+    pub fn wait_for_busy(&mut self) -> Result<(), Error<SpiError>> {
+        self.check_wedged()?;
+        self.chip_select
+            .select(&mut self.spi, &mut self.delay, &mut self.timer)?;
+        Ok(())
     }
 
-    fn expect_byte(spi: &mut Spi, target_char: u8) -> Result<(), Error<SpiError>> {
-        let v = spi.transfer_byte().map_err(Error::spi)?;
-
-        if v == target_char {
-            Ok(())
-        } else {
-            Err(Error::UnexpectedResponse(target_char, v))
+    fn check_wedged(&self) -> Result<(), Error<SpiError>> {
+        match self.config.wedged_after_timeouts {
+            Some(limit) if self.chip_select.consecutive_timeouts() >= limit => {
+                Err(Error::DeviceWedged)
+            }
+            _ => Ok(()),
         }
     }
 
-    pub fn wait_for_busy(&mut self) -> Result<(), Error<SpiError>> {
-        let mut spi = self.chip_select.select(&mut self.spi, &mut self.delay)?;
-        Ok(())
-    }
-
     fn send_command(
-        &mut self,
+        link: &mut Link<Spi, Delay, Timer>,
         cmd: NinaCommand,
         params: Params<SendParam>,
     ) -> Result<(), Error<SpiError>> {
-        let mut spi = self.chip_select.select(&mut self.spi, &mut self.delay)?;
-
         let cmd_byte: u8 = cmd.into();
         let mut sent_len: usize = 0;
 
         let use_16_bit_length = params.use_16_bit_length();
 
-        spi.write(&[
-            NinaCommand::Start.into(),
-            // Pedantic to mask out the top bit, since none of the commands use it.
-            cmd_byte & !Self::REPLY_FLAG,
-            params.len(),
-        ])
-        .map_err(Error::spi)?;
+        link.write(
+            &[
+                NinaCommand::Start.into(),
+                // Pedantic to mask out the top bit, since none of the commands use it.
+                cmd_byte & !Self::REPLY_FLAG,
+                params.len(),
+            ],
+            Phase::SendHeader,
+        )?;
 
         sent_len += 3;
 
-        let mut write_len = |spi: &mut Spi, len: usize| -> Result<(), Error<SpiError>> {
-            sent_len += len;
-
-            if use_16_bit_length {
-                sent_len += 2;
-                spi.write(&(len as u16).to_be_bytes()).map_err(Error::spi)?;
-            } else {
-                sent_len += 1;
-                spi.write(&[len as u8]).map_err(Error::spi)?;
-            };
-
-            Ok(())
-        };
+        let mut write_len =
+            |link: &mut Link<Spi, Delay, Timer>, len: usize| -> Result<(), Error<SpiError>> {
+                sent_len += len;
 
-        let write_bytes: fn(&mut Spi, &mut dyn Iterator<Item = u8>) -> Result<(), Error<SpiError>> =
-            |spi: &mut Spi, bytes: &mut dyn Iterator<Item = u8>| {
-                for word in bytes.into_iter() {
-                    block!(spi.send(word.clone())).map_err(Error::spi)?;
-                    block!(spi.read()).map_err(Error::spi)?;
-                }
+                if use_16_bit_length {
+                    sent_len += 2;
+                    link.write(&(len as u16).to_be_bytes(), Phase::SendBody)?;
+                } else {
+                    sent_len += 1;
+                    link.write(&[len as u8], Phase::SendBody)?;
+                };
 
                 Ok(())
-                //spi.write_iter(bytes).map_err(Error::spi)
             };
 
         for p in params {
             match p {
                 SendParam::Byte(b) => {
-                    write_len(&mut spi, 1)?;
-                    write_bytes(&mut spi, &mut [*b].iter().cloned())?;
+                    write_len(link, 1)?;
+                    link.write_iter(&mut [*b].iter().cloned())?;
                 }
 
                 SendParam::Word(w) => {
-                    write_len(&mut spi, 2)?;
-                    write_bytes(&mut spi, &mut w.to_be_bytes().iter().cloned())?;
+                    write_len(link, 2)?;
+                    link.write_iter(&mut w.to_be_bytes().iter().cloned())?;
                 }
 
                 SendParam::LEWord(w) => {
-                    write_len(&mut spi, 2)?;
-                    write_bytes(&mut spi, &mut w.to_le_bytes().iter().cloned())?;
+                    write_len(link, 2)?;
+                    link.write_iter(&mut w.to_le_bytes().iter().cloned())?;
+                }
+
+                SendParam::DWord(w) => {
+                    write_len(link, 4)?;
+                    link.write_iter(&mut w.to_be_bytes().iter().cloned())?;
+                }
+
+                SendParam::LEDWord(w) => {
+                    write_len(link, 4)?;
+                    link.write_iter(&mut w.to_le_bytes().iter().cloned())?;
+                }
+
+                SendParam::SignedDWord(w) => {
+                    write_len(link, 4)?;
+                    link.write_iter(&mut w.to_be_bytes().iter().cloned())?;
+                }
+
+                SendParam::LESignedDWord(w) => {
+                    write_len(link, 4)?;
+                    link.write_iter(&mut w.to_le_bytes().iter().cloned())?;
                 }
 
                 SendParam::Bytes(it) => {
-                    write_len(&mut spi, it.len())?;
-                    write_bytes(&mut spi, it)?;
+                    write_len(link, it.len())?;
+                    link.write_iter(it)?;
                 }
             };
         }
 
-        spi.write(&[NinaCommand::End.into()]).map_err(Error::spi)?;
+        link.write(&[NinaCommand::End.into()], Phase::SendBody)?;
 
         sent_len += 1;
 
-        // Pad out request to a multiple of 4 bytes.
-        while sent_len % 4 != 0 {
-            spi.write(&[0]).map_err(Error::spi)?;
+        // Pad out request to a multiple of 4 bytes (or whatever the config
+        // says). is_multiple_of would need Rust 1.87.
+        #[allow(clippy::manual_is_multiple_of)]
+        while sent_len % link.command_alignment != 0 {
+            link.write(&[0], Phase::SendBody)?;
             sent_len += 1;
         }
 
@@ -235,32 +257,30 @@ where
     }
 
     fn receive_response(
-        &mut self,
+        link: &mut Link<Spi, Delay, Timer>,
         cmd: NinaCommand,
         params: Params<RecvParam>,
     ) -> Result<(), Error<SpiError>> {
-        let mut spi = self.chip_select.select(&mut self.spi, &mut self.delay)?;
-
         let cmd_byte: u8 = cmd.into();
-        Self::wait_for_response_start(&mut spi, &mut self.delay)?;
+        link.wait_for_response_start()?;
         // We expect that the server sends back the same command, with the high bit
         // set to indicate a reply.
-        Self::expect_byte(&mut spi, Self::REPLY_FLAG | cmd_byte)?;
+        link.expect_byte(Phase::RecvHeader, Self::REPLY_FLAG | cmd_byte)?;
 
         let use_16_bit_length = params.use_16_bit_length();
 
-        let read_len = |spi: &mut Spi, expect: Option<usize>| -> Result<usize, Error<SpiError>> {
-            let len: usize;
-
-            if use_16_bit_length {
+        let read_len = |link: &mut Link<Spi, Delay, Timer>,
+                        expect: Option<usize>|
+         -> Result<usize, Error<SpiError>> {
+            let len: usize = if use_16_bit_length {
                 let bits = [
-                    spi.transfer_byte().map_err(Error::spi)?,
-                    spi.transfer_byte().map_err(Error::spi)?,
+                    link.read_byte(Phase::RecvBody)?,
+                    link.read_byte(Phase::RecvBody)?,
                 ];
 
-                len = u16::from_be_bytes(bits) as usize;
+                u16::from_be_bytes(bits) as usize
             } else {
-                len = spi.transfer_byte().map_err(Error::spi)? as usize;
+                link.read_byte(Phase::RecvBody)? as usize
             };
 
             if let Some(expect) = expect {
@@ -269,82 +289,151 @@ where
                 }
             }
 
-            return Ok(len);
+            Ok(len)
         };
 
-        let param_count: u8 = spi.transfer_byte().map_err(Error::spi)?;
+        let param_count: u8 = link.read_byte(Phase::RecvHeader)?;
+        link.param_count = Some(param_count);
         let mut param_idx: u8 = 0;
 
         for param_handler in params {
             if param_idx == param_count {
                 match param_handler {
                     RecvParam::OptionalByte(_) => continue,
+                    RecvParam::BufferList(_, _, count) => {
+                        **count = 0;
+                        continue;
+                    }
                     _ => return Err(Error::MissingParam(param_idx)),
                 }
             };
 
             match param_handler {
                 RecvParam::Ack => {
-                    read_len(&mut spi, Some(1))?;
-                    Self::expect_byte(&mut spi, NinaResponse::Ack.into())?;
+                    read_len(link, Some(1))?;
+                    link.expect_byte(Phase::RecvBody, NinaResponse::Ack.into())?;
                 }
 
                 RecvParam::ExpectByte(b) => {
-                    read_len(&mut spi, Some(1))?;
-                    Self::expect_byte(&mut spi, *b)?;
+                    read_len(link, Some(1))?;
+                    link.expect_byte(Phase::RecvBody, *b)?;
                 }
 
                 RecvParam::Byte(ref mut b) => {
-                    read_len(&mut spi, Some(1))?;
-                    **b = spi.transfer_byte().map_err(Error::spi)?;
+                    read_len(link, Some(1))?;
+                    **b = link.read_byte(Phase::RecvBody)?;
                 }
 
                 RecvParam::OptionalByte(ref mut op) => {
-                    read_len(&mut spi, Some(1))?;
-                    op.replace(spi.transfer_byte().map_err(Error::spi)?);
+                    read_len(link, Some(1))?;
+                    op.replace(link.read_byte(Phase::RecvBody)?);
                 }
 
                 RecvParam::Word(ref mut w) => {
-                    read_len(&mut spi, Some(2))?;
+                    read_len(link, Some(2))?;
 
                     let bits = [
-                        spi.transfer_byte().map_err(Error::spi)?,
-                        spi.transfer_byte().map_err(Error::spi)?,
+                        link.read_byte(Phase::RecvBody)?,
+                        link.read_byte(Phase::RecvBody)?,
                     ];
 
                     **w = u16::from_be_bytes(bits);
                 }
 
                 RecvParam::LEWord(ref mut w) => {
-                    read_len(&mut spi, Some(2))?;
+                    read_len(link, Some(2))?;
 
                     let bits = [
-                        spi.transfer_byte().map_err(Error::spi)?,
-                        spi.transfer_byte().map_err(Error::spi)?,
+                        link.read_byte(Phase::RecvBody)?,
+                        link.read_byte(Phase::RecvBody)?,
                     ];
 
                     **w = u16::from_le_bytes(bits);
                 }
 
+                RecvParam::DWord(ref mut w) => {
+                    read_len(link, Some(4))?;
+                    **w = u32::from_be_bytes(link.read_dword()?);
+                }
+
+                RecvParam::LEDWord(ref mut w) => {
+                    read_len(link, Some(4))?;
+                    **w = u32::from_le_bytes(link.read_dword()?);
+                }
+
+                RecvParam::SignedDWord(ref mut w) => {
+                    read_len(link, Some(4))?;
+                    **w = i32::from_be_bytes(link.read_dword()?);
+                }
+
+                RecvParam::LESignedDWord(ref mut w) => {
+                    read_len(link, Some(4))?;
+                    **w = i32::from_le_bytes(link.read_dword()?);
+                }
+
                 RecvParam::ByteArray(arr) => {
-                    read_len(&mut spi, Some(arr.len()))?;
+                    read_len(link, Some(arr.len()))?;
 
-                    for i in 0..arr.len() {
-                        arr[i] = spi.transfer_byte().map_err(Error::spi)?;
+                    for b in arr.iter_mut() {
+                        *b = link.read_byte(Phase::RecvBody)?;
+                    }
+                }
+
+                RecvParam::ByteArrayUpTo(arr) => {
+                    let len = read_len(link, None)?;
+
+                    for i in 0..len {
+                        let b = link.read_byte(Phase::RecvBody)?;
+
+                        if let Some(slot) = arr.get_mut(i) {
+                            *slot = b;
+                        }
                     }
                 }
 
                 RecvParam::Buffer(arr, ref mut len) => {
-                    **len = read_len(&mut spi, None)?;
+                    **len = read_len(link, None)?;
 
-                    for i in 0..**len {
-                        arr[i] = spi.transfer_byte().map_err(Error::spi)?;
+                    if **len > arr.len() {
+                        return Err(Error::MismatchedParamSize(arr.len(), **len));
                     }
+
+                    link.read_into(&mut arr[..**len], Phase::RecvBody)?;
                 }
 
                 RecvParam::Socket(ref mut socket) => {
-                    read_len(&mut spi, Some(1))?;
-                    *socket.num_mut() = spi.transfer_byte().map_err(Error::spi)?;
+                    read_len(link, Some(1))?;
+                    *socket.num_mut() = link.read_byte(Phase::RecvBody)?;
+                }
+
+                RecvParam::BufferList(bufs, lens, count) => {
+                    **count = 0;
+
+                    while param_idx < param_count {
+                        let len = read_len(link, None)?;
+
+                        if **count < core::cmp::min(bufs.len(), lens.len()) {
+                            let buf = &mut bufs[**count];
+
+                            if len > buf.len() {
+                                return Err(Error::MismatchedParamSize(buf.len(), len));
+                            }
+
+                            link.read_into(&mut buf[..len], Phase::RecvBody)?;
+
+                            lens[**count] = len;
+                        } else {
+                            // No room left, so drop the param on the floor.
+                            for _ in 0..len {
+                                link.read_byte(Phase::RecvBody)?;
+                            }
+                        }
+
+                        **count += 1;
+                        param_idx += 1;
+                    }
+
+                    continue;
                 }
             };
 
@@ -358,14 +447,90 @@ where
         Ok(())
     }
 
+    // Every command is a request followed by its response. The chip drops
+    // busy once it has a response ready, and it expects CS to be released
+    // between the two so that it can go process the request. (Unless
+    // Config::hold_chip_select is set, for firmware that wants the whole
+    // exchange in a single transaction.)
+    //
+    // Since this takes &mut self (and ConnectedSocket holds on to the &mut
+    // WifiNina) nothing else in Rust can issue a command in between. If the
+    // SPI bus is shared with other devices, though, it’s up to the caller to
+    // make sure nothing else (an interrupt handler, say) clocks the bus while
+    // a command is in flight, since the chip has no way to tell that traffic
+    // apart from the response it’s expecting to send.
     fn send_and_receive(
         &mut self,
         command: NinaCommand,
         send_params: Params<SendParam>,
         recv_params: Params<RecvParam>,
     ) -> Result<(), Error<SpiError>> {
-        self.send_command(command, send_params)?;
-        self.receive_response(command, recv_params)
+        let result = self.exchange(command, send_params, recv_params);
+
+        match result {
+            Err(Error::ResponseTimeout) => self.count(Event::ResponseTimeout),
+            Err(Error::ChipSelectTimeout) => self.count(Event::ChipSelectTimeout),
+            _ => {}
+        }
+
+        result
+    }
+
+    fn exchange(
+        &mut self,
+        command: NinaCommand,
+        send_params: Params<SendParam>,
+        recv_params: Params<RecvParam>,
+    ) -> Result<(), Error<SpiError>> {
+        let (send_params, recv_params) = match self.config.data_param_length {
+            Some(length) if command.is_data_command() => (
+                send_params.with_param_length(length),
+                recv_params.with_param_length(length),
+            ),
+            _ => (send_params, recv_params),
+        };
+
+        self.check_wedged()?;
+
+        self.last_param_count = None;
+
+        if self.config.hold_chip_select {
+            let mut spi =
+                self.chip_select
+                    .select(&mut self.spi, &mut self.delay, &mut self.timer)?;
+            let mut link = Link::new(&mut *spi, &mut self.delay, &mut self.timer, &self.config);
+
+            Self::send_command(&mut link, command, send_params)?;
+
+            let result = Self::receive_response(&mut link, command, recv_params);
+            self.last_param_count = link.param_count;
+            return result;
+        }
+
+        {
+            let mut spi =
+                self.chip_select
+                    .select(&mut self.spi, &mut self.delay, &mut self.timer)?;
+            let mut link = Link::new(&mut *spi, &mut self.delay, &mut self.timer, &self.config);
+
+            Self::send_command(&mut link, command, send_params)?;
+        }
+
+        let mut spi = self
+            .chip_select
+            .select(&mut self.spi, &mut self.delay, &mut self.timer)?;
+        let mut link = Link::new(&mut *spi, &mut self.delay, &mut self.timer, &self.config);
+
+        let result = Self::receive_response(&mut link, command, recv_params);
+        self.last_param_count = link.param_count;
+        result
+    }
+
+    // How many params the chip sent in its last response, or None if it
+    // didn’t get as far as saying. For working out the shape of a response
+    // after a MissingParam or UnexpectedParam.
+    pub fn last_param_count(&self) -> Option<u8> {
+        self.last_param_count
     }
 
     pub fn set_debug(&mut self, enabled: bool) -> Result<(), Error<SpiError>> {
@@ -375,12 +540,171 @@ where
             Params::of(&mut [RecvParam::Ack]),
         )
     }
+
+    // An escape hatch for firmware with commands of its own: a single
+    // chip-selected transaction that clocks out tx while reading into rx,
+    // with none of the command framing. It runs for as long as the longer of
+    // the two, sending zeros past the end of tx and dropping what’s read past
+    // the end of rx.
+    pub fn transfer_raw(&mut self, tx: &[u8], rx: &mut [u8]) -> Result<(), Error<SpiError>> {
+        self.check_wedged()?;
+
+        let mut spi = self
+            .chip_select
+            .select(&mut self.spi, &mut self.delay, &mut self.timer)?;
+        let mut link = Link::new(&mut *spi, &mut self.delay, &mut self.timer, &self.config);
+
+        for i in 0..core::cmp::max(tx.len(), rx.len()) {
+            let b = link.transfer(tx.get(i).copied().unwrap_or(0))?;
+
+            if let Some(slot) = rx.get_mut(i) {
+                *slot = b;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The selected SPI bus, along with what’s needed to pace the bytes going
+// over it.
+struct Link<'a, Spi, Delay, Timer> {
+    spi: &'a mut Spi,
+    delay: &'a mut Delay,
+    timer: &'a mut Timer,
+    inter_byte_delay_us: u16,
+    read_burst_len: usize,
+    command_alignment: usize,
+
+    // Set once receive_response has read it.
+    param_count: Option<u8>,
+}
+
+impl<'a, Spi, SpiError, Delay, Timer> Link<'a, Spi, Delay, Timer>
+where
+    Spi:
+        FullDuplex<u8, Error = SpiError> + embedded_hal::blocking::spi::Write<u8, Error = SpiError>,
+    SpiError: Debug,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    Timer: crate::util::timer::Timer,
+{
+    fn new(spi: &'a mut Spi, delay: &'a mut Delay, timer: &'a mut Timer, config: &Config) -> Self {
+        Link {
+            spi,
+            delay,
+            timer,
+            inter_byte_delay_us: config.inter_byte_delay_us,
+            read_burst_len: core::cmp::max(config.read_burst_len, 1) as usize,
+            command_alignment: core::cmp::max(config.command_alignment, 1) as usize,
+            param_count: None,
+        }
+    }
+
+    fn settle(&mut self) {
+        if self.inter_byte_delay_us > 0 {
+            self.delay.delay_us(self.inter_byte_delay_us);
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8], phase: Phase) -> Result<(), Error<SpiError>> {
+        if self.inter_byte_delay_us == 0 {
+            return self.spi.write(bytes).map_err(Error::spi(phase));
+        }
+
+        for b in bytes {
+            self.spi.write(&[*b]).map_err(Error::spi(phase))?;
+            self.settle();
+        }
+
+        Ok(())
+    }
+
+    fn write_iter(&mut self, bytes: &mut dyn Iterator<Item = u8>) -> Result<(), Error<SpiError>> {
+        for word in bytes {
+            block!(self.spi.send(word)).map_err(Error::spi(Phase::SendBody))?;
+            block!(self.spi.read()).map_err(Error::spi(Phase::SendBody))?;
+            self.settle();
+        }
+
+        Ok(())
+        //spi.write_iter(bytes).map_err(Error::spi(Phase::SendBody))
+    }
+
+    fn transfer(&mut self, word: u8) -> Result<u8, Error<SpiError>> {
+        block!(self.spi.send(word)).map_err(Error::spi(Phase::SendBody))?;
+        let byte = block!(self.spi.read()).map_err(Error::spi(Phase::SendBody))?;
+        self.settle();
+        Ok(byte)
+    }
+
+    fn read_byte(&mut self, phase: Phase) -> Result<u8, Error<SpiError>> {
+        let byte = self.spi.transfer_byte().map_err(Error::spi(phase))?;
+        self.settle();
+        Ok(byte)
+    }
+
+    // Fills buf in bursts of read_burst_len bytes, settling between them.
+    fn read_into(&mut self, buf: &mut [u8], phase: Phase) -> Result<(), Error<SpiError>> {
+        for burst in buf.chunks_mut(self.read_burst_len) {
+            for b in burst.iter_mut() {
+                *b = self.spi.transfer_byte().map_err(Error::spi(phase))?;
+            }
+
+            self.settle();
+        }
+
+        Ok(())
+    }
+
+    // The four bytes of a 32-bit param, in the order they were sent.
+    fn read_dword(&mut self) -> Result<[u8; 4], Error<SpiError>> {
+        Ok([
+            self.read_byte(Phase::RecvBody)?,
+            self.read_byte(Phase::RecvBody)?,
+            self.read_byte(Phase::RecvBody)?,
+            self.read_byte(Phase::RecvBody)?,
+        ])
+    }
+
+    fn expect_byte(&mut self, phase: Phase, target_char: u8) -> Result<(), Error<SpiError>> {
+        let v = self.read_byte(phase)?;
+
+        if v == target_char {
+            Ok(())
+        } else {
+            Err(Error::UnexpectedResponse(target_char, v))
+        }
+    }
+
+    fn wait_for_response_start(&mut self) -> Result<(), Error<SpiError>> {
+        self.timer.start(100);
+
+        loop {
+            let byte = self.read_byte(Phase::RecvHeader)?;
+
+            if byte == NinaCommand::Start.into() {
+                return Ok(());
+            } else if byte == NinaCommand::Error.into() {
+                return Err(Error::ErrorResponse);
+            }
+            self.delay.delay_ms(1);
+
+            if self.timer.expired(1) {
+                return Err(Error::ResponseTimeout);
+            }
+        }
+    }
 }
 
 pub enum SendParam<'a> {
     Byte(u8),
     Word(u16),
     LEWord(u16),
+    DWord(u32),
+    LEDWord(u32),
+    SignedDWord(i32),
+    LESignedDWord(i32),
     Bytes(&'a mut dyn ExactSizeIterator<Item = u8>),
 }
 
@@ -393,8 +717,22 @@ pub enum RecvParam<'a> {
     ExpectByte(u8),
     Word(&'a mut u16),
     LEWord(&'a mut u16),
+    DWord(&'a mut u32),
+    LEDWord(&'a mut u32),
+    SignedDWord(&'a mut i32),
+    LESignedDWord(&'a mut i32),
+    // Must be exactly as long as the array, or it’s
+    // Error::MismatchedParamSize.
     ByteArray(&'a mut [u8]),
+    // Fills as much of the array as the param has, discarding any bytes
+    // beyond its length. Bytes the param didn’t reach are left as they were.
+    ByteArrayUpTo(&'a mut [u8]),
     Buffer(&'a mut [u8], &'a mut usize),
+    // Takes all of the remaining params, however many the chip sends, copying
+    // each into the next buffer and its length into the matching slot. The
+    // last value is set to how many params the chip sent. Params beyond the
+    // number of buffers are read and discarded, so this must come last.
+    BufferList(&'a mut [&'a mut [u8]], &'a mut [usize], &'a mut usize),
 }
 
 pub struct Params<'a, P> {
@@ -424,10 +762,21 @@ impl<'a, P> Params<'a, P> {
         }
     }
 
+    pub fn with_param_length(self, length: ParamLength) -> Self {
+        Params {
+            params: self.params,
+            use_16_bit_length: length == ParamLength::U16,
+        }
+    }
+
     pub fn len(&self) -> u8 {
         self.params.len() as u8
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
     pub fn use_16_bit_length(&self) -> bool {
         self.use_16_bit_length
     }
@@ -438,6 +787,340 @@ impl<'a, P> core::iter::IntoIterator for Params<'a, P> {
     type IntoIter = core::slice::IterMut<'a, P>;
 
     fn into_iter(self) -> core::slice::IterMut<'a, P> {
-        self.params.into_iter()
+        self.params.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::wifi::WifiStatus, mock};
+
+    // The opcodes as the firmware names and numbers them (nina-fw’s
+    // CommandHandler.cpp, mirrored in the Arduino library’s
+    // utility/wifi_spi.h). There’s deliberately no wildcard arm, so a new
+    // NinaCommand won’t compile until it has been added here too.
+    fn firmware_opcode(cmd: NinaCommand) -> (&'static str, u8) {
+        use NinaCommand::*;
+
+        match cmd {
+            SetNetwork => ("SET_NET_CMD", 0x10),
+            SetNetworkAndPassphrase => ("SET_PASSPHRASE_CMD", 0x11),
+            SetKey => ("SET_KEY_CMD", 0x12),
+            SetIpConfig => ("SET_IP_CONFIG_CMD", 0x14),
+            SetDnsConfig => ("SET_DNS_CONFIG_CMD", 0x15),
+            SetHostname => ("SET_HOSTNAME_CMD", 0x16),
+            SetPowerMode => ("SET_POWER_MODE_CMD", 0x17),
+            SetApNetwork => ("SET_AP_NET_CMD", 0x18),
+            SetApPassphrase => ("SET_AP_PASSPHRASE_CMD", 0x19),
+            SetDebug => ("SET_DEBUG_CMD", 0x1A),
+            GetReasonCode => ("GET_REASON_CODE_CMD", 0x1F),
+
+            GetConnectionStatus => ("GET_CONN_STATUS_CMD", 0x20),
+            GetIpAddress => ("GET_IPADDR_CMD", 0x21),
+            GetMacAddress => ("GET_MACADDR_CMD", 0x22),
+            GetCurrentSsid => ("GET_CURR_SSID_CMD", 0x23),
+            GetCurrentBssid => ("GET_CURR_BSSID_CMD", 0x24),
+            GetCurrentRssi => ("GET_CURR_RSSI_CMD", 0x25),
+            GetCurrentEnct => ("GET_CURR_ENCT_CMD", 0x26),
+            ScanNetworks => ("SCAN_NETWORKS", 0x27),
+            StartServerTcp => ("START_SERVER_TCP_CMD", 0x28),
+            GetStateTcp => ("GET_STATE_TCP_CMD", 0x29),
+            DataSentTcp => ("DATA_SENT_TCP_CMD", 0x2A),
+            AvailableDataTcp => ("AVAIL_DATA_TCP_CMD", 0x2B),
+            GetDataTcp => ("GET_DATA_TCP_CMD", 0x2C),
+            StartClientTcp => ("START_CLIENT_TCP_CMD", 0x2D),
+            StopClientTcp => ("STOP_CLIENT_TCP_CMD", 0x2E),
+            GetClientStateTcp => ("GET_CLIENT_STATE_TCP_CMD", 0x2F),
+
+            Disconnect => ("DISCONNECT_CMD", 0x30),
+            GetIdxRssi => ("GET_IDX_RSSI_CMD", 0x32),
+            GetIdxEnct => ("GET_IDX_ENCT_CMD", 0x33),
+            RequestHostByName => ("REQ_HOST_BY_NAME_CMD", 0x34),
+            GetHostByName => ("GET_HOST_BY_NAME_CMD", 0x35),
+            StartScanNetworks => ("START_SCAN_NETWORKS", 0x36),
+            GetFirmwareVersion => ("GET_FW_VERSION_CMD", 0x37),
+            SendUdpData => ("SEND_DATA_UDP_CMD", 0x39),
+            GetRemoteData => ("GET_REMOTE_DATA_CMD", 0x3A),
+            GetTime => ("GET_TIME_CMD", 0x3B),
+            GetIdxBssid => ("GET_IDX_BSSID_CMD", 0x3C),
+            GetIdxChannel => ("GET_IDX_CHANNEL_CMD", 0x3D),
+            Ping => ("PING_CMD", 0x3E),
+            GetSocket => ("GET_SOCKET_CMD", 0x3F),
+
+            SetClientCert => ("SET_CLIENT_CERT", 0x40),
+            SetCertKey => ("SET_CERT_KEY", 0x41),
+            SendDataTcp => ("SEND_DATA_TCP_CMD", 0x44),
+            GetDatabufTcp => ("GET_DATABUF_TCP_CMD", 0x45),
+            InsertDataBuf => ("INSERT_DATABUF_CMD", 0x46),
+            SetEnterpriseIdent => ("SET_ENT_IDENT_CMD", 0x4A),
+            SetEnterpriseUsername => ("SET_ENT_UNAME_CMD", 0x4B),
+            SetEnterprisePassword => ("SET_ENT_PASSWD_CMD", 0x4C),
+            SetEnterpriseEnable => ("SET_ENT_ENABLE_CMD", 0x4F),
+
+            SetPinMode => ("SET_PIN_MODE", 0x50),
+            SetDigitalWrite => ("SET_DIGITAL_WRITE", 0x51),
+            SetAnalogWrite => ("SET_ANALOG_WRITE", 0x52),
+
+            Start => ("START_CMD", 0xE0),
+            End => ("END_CMD", 0xEE),
+            Error => ("ERR_CMD", 0xEF),
+        }
+    }
+
+    #[test]
+    fn opcodes_match_the_firmware() {
+        use NinaCommand::*;
+
+        let all = [
+            SetNetwork,
+            SetNetworkAndPassphrase,
+            SetKey,
+            SetIpConfig,
+            SetDnsConfig,
+            SetHostname,
+            SetPowerMode,
+            SetApNetwork,
+            SetApPassphrase,
+            SetDebug,
+            GetReasonCode,
+            GetConnectionStatus,
+            GetIpAddress,
+            GetMacAddress,
+            GetCurrentSsid,
+            GetCurrentBssid,
+            GetCurrentRssi,
+            GetCurrentEnct,
+            ScanNetworks,
+            StartServerTcp,
+            GetStateTcp,
+            DataSentTcp,
+            AvailableDataTcp,
+            GetDataTcp,
+            StartClientTcp,
+            StopClientTcp,
+            GetClientStateTcp,
+            Disconnect,
+            GetIdxRssi,
+            GetIdxEnct,
+            RequestHostByName,
+            GetHostByName,
+            StartScanNetworks,
+            GetFirmwareVersion,
+            SendUdpData,
+            GetRemoteData,
+            GetTime,
+            GetIdxBssid,
+            GetIdxChannel,
+            Ping,
+            GetSocket,
+            SetClientCert,
+            SetCertKey,
+            SendDataTcp,
+            GetDatabufTcp,
+            InsertDataBuf,
+            SetEnterpriseIdent,
+            SetEnterpriseUsername,
+            SetEnterprisePassword,
+            SetEnterpriseEnable,
+            SetPinMode,
+            SetDigitalWrite,
+            SetAnalogWrite,
+            Start,
+            End,
+            Error,
+        ];
+
+        for cmd in all.iter().copied() {
+            let (name, opcode) = firmware_opcode(cmd);
+            assert_eq!(u8::from(cmd), opcode, "{:?} should be {}", cmd, name);
+        }
+    }
+
+    // Sends a command with a single Bytes param of every length from 0 to
+    // 12, and checks that each one comes out as header + length field +
+    // data + End, zero-padded to a multiple of 4.
+    fn check_padding(use_16_bit_length: bool) {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let length_field = if use_16_bit_length { 2 } else { 1 };
+
+        for len in 0..=12usize {
+            let data = [0xAAu8; 12];
+
+            bus.borrow_mut().sent.clear();
+            bus.borrow_mut()
+                .respond_with(NinaCommand::SendDataTcp, &[&[1]]);
+
+            let mut bytes = data[..len].iter().cloned();
+            let mut params = [SendParam::Bytes(&mut bytes)];
+            let params = if use_16_bit_length {
+                Params::with_16_bit_length(&mut params)
+            } else {
+                Params::of(&mut params)
+            };
+
+            wifi.send_and_receive(
+                NinaCommand::SendDataTcp,
+                params,
+                Params::of(&mut [RecvParam::Ack]),
+            )
+            .unwrap();
+
+            let sent = &bus.borrow().sent;
+            let unpadded = 3 + length_field + len + 1;
+
+            assert_eq!(sent.len() % 4, 0, "len {}", len);
+            assert_eq!(sent.len(), unpadded.div_ceil(4) * 4, "len {}", len);
+            assert_eq!(sent[unpadded - 1], NinaCommand::End.into(), "len {}", len);
+            assert!(sent[unpadded..].iter().all(|&b| b == 0), "len {}", len);
+        }
+    }
+
+    #[test]
+    fn pads_commands_with_8_bit_lengths() {
+        check_padding(false);
+    }
+
+    #[test]
+    fn pads_commands_with_16_bit_lengths() {
+        check_padding(true);
+    }
+
+    #[test]
+    fn fails_fast_once_the_chip_is_wedged() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        wifi.config_mut().wedged_after_timeouts = Some(2);
+        bus.borrow_mut().busy_stuck = true;
+
+        assert!(matches!(wifi.wifi_status(), Err(Error::ChipSelectTimeout)));
+        assert!(matches!(wifi.wifi_status(), Err(Error::ChipSelectTimeout)));
+
+        // Without waiting on the busy pin at all.
+        let polls = bus.borrow().busy_polls;
+        assert!(matches!(wifi.wifi_status(), Err(Error::DeviceWedged)));
+        assert_eq!(bus.borrow().busy_polls, polls);
+
+        bus.borrow_mut().busy_stuck = false;
+        wifi.reset(&mut mock::MockPin).unwrap();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetConnectionStatus, &[&[3]]);
+
+        assert_eq!(wifi.wifi_status().unwrap(), WifiStatus::Connected);
+    }
+
+    #[test]
+    fn hold_chip_select_selects_once_per_command() {
+        let (mut wifi, bus) = mock::wifi_nina();
+
+        for (hold, selects) in [(false, 2), (true, 1)] {
+            wifi.config_mut().hold_chip_select = hold;
+            bus.borrow_mut().selects = 0;
+            bus.borrow_mut()
+                .respond_with(NinaCommand::GetConnectionStatus, &[&[3]]);
+
+            assert_eq!(wifi.wifi_status().unwrap(), WifiStatus::Connected);
+            assert_eq!(bus.borrow().selects, selects, "hold {}", hold);
+            assert_eq!(&bus.borrow().sent[..4], &[0xE0, 0x20, 0, 0xEE]);
+
+            bus.borrow_mut().sent.clear();
+        }
+    }
+
+    #[test]
+    fn pads_to_the_configured_alignment() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let command: &[u8] = &[0xE0, 0x1A, 1, 1, 1, 0xEE];
+
+        for (alignment, len) in [(4, 8), (0, 6), (1, 6), (16, 16)] {
+            wifi.config_mut().command_alignment = alignment;
+            bus.borrow_mut().sent.clear();
+            bus.borrow_mut()
+                .respond_with(NinaCommand::SetDebug, &[&[1]]);
+
+            wifi.set_debug(true).unwrap();
+
+            let sent = &bus.borrow().sent;
+            assert_eq!(&sent[..6], command, "alignment {}", alignment);
+            assert_eq!(sent.len(), len, "alignment {}", alignment);
+            assert!(sent[6..].iter().all(|&b| b == 0), "alignment {}", alignment);
+        }
+    }
+
+    #[test]
+    fn byte_array_up_to_discards_extra_bytes() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetIpAddress, &[&[1, 2, 3, 4, 5, 6], &[7]]);
+
+        let mut arr = [0u8; 4];
+        let mut next: u8 = 0;
+
+        wifi.send_and_receive(
+            NinaCommand::GetIpAddress,
+            Params::none(),
+            Params::of(&mut [
+                RecvParam::ByteArrayUpTo(&mut arr),
+                RecvParam::Byte(&mut next),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(arr, [1, 2, 3, 4]);
+        assert_eq!(next, 7);
+    }
+
+    #[test]
+    fn reads_dwords_in_both_byte_orders() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let bytes: &[u8] = &[0x12, 0x34, 0x56, 0x78];
+        let negative: &[u8] = &[0xC4, 0xFF, 0xFF, 0xFF];
+        bus.borrow_mut().respond_with(
+            NinaCommand::GetCurrentRssi,
+            &[bytes, bytes, negative, negative],
+        );
+
+        let (mut be, mut le, mut signed_be, mut signed_le) = (0, 0, 0, 0);
+
+        wifi.send_and_receive(
+            NinaCommand::GetCurrentRssi,
+            Params::none(),
+            Params::of(&mut [
+                RecvParam::DWord(&mut be),
+                RecvParam::LEDWord(&mut le),
+                RecvParam::SignedDWord(&mut signed_be),
+                RecvParam::LESignedDWord(&mut signed_le),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(be, 0x1234_5678);
+        assert_eq!(le, 0x7856_3412);
+        assert_eq!(signed_be, -0x3B00_0001);
+        assert_eq!(signed_le, -60);
+    }
+
+    #[test]
+    fn sends_dwords_in_both_byte_orders() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SetDebug, &[&[1]]);
+
+        wifi.send_and_receive(
+            NinaCommand::SetDebug,
+            Params::of(&mut [
+                SendParam::DWord(0x1234_5678),
+                SendParam::LEDWord(0x1234_5678),
+                SendParam::LESignedDWord(-60),
+            ]),
+            Params::of(&mut [RecvParam::Ack]),
+        )
+        .unwrap();
+
+        let sent = &bus.borrow().sent;
+
+        assert_eq!(
+            &sent[3..18],
+            &[4, 0x12, 0x34, 0x56, 0x78, 4, 0x78, 0x56, 0x34, 0x12, 4, 0xC4, 0xFF, 0xFF, 0xFF]
+        );
     }
 }
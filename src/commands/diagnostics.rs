@@ -0,0 +1,294 @@
+use embedded_hal::{
+    digital::v2::{InputPin, OutputPin},
+    spi::FullDuplex,
+};
+
+use crate::{commands::*, Error, WifiNina};
+
+// Outcome of each step of self_test. A step that failed is None (or false)
+// rather than an error, so that one failure doesn’t hide the others.
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    pub reset: bool,
+    pub firmware_version: Option<heapless::String<16>>,
+    pub mac_address: Option<[u8; 6]>,
+    pub networks_found: Option<u8>,
+}
+
+impl SelfTestReport {
+    // True if every step worked and the scan saw at least one network.
+    pub fn passed(&self) -> bool {
+        self.reset
+            && self.firmware_version.is_some()
+            && self.mac_address.is_some()
+            && self.networks_found.unwrap_or(0) > 0
+    }
+}
+
+// Tally from link_test. Every iteration lands in exactly one of the counts.
+#[derive(Debug, Clone, Default)]
+pub struct LinkTestResult {
+    pub iterations: u16,
+    pub ok: u16,
+    // Well-formed responses whose contents differed from the first one.
+    pub mismatches: u16,
+    // Responses that didn’t parse: wrong bytes, lengths or param counts.
+    pub framing_errors: u16,
+    // The chip never signalled ready, or never started its response.
+    pub timeouts: u16,
+}
+
+impl LinkTestResult {
+    // Fraction of iterations that went wrong, from 0.0 to 1.0.
+    pub fn error_rate(&self) -> f32 {
+        if self.iterations == 0 {
+            return 0.0;
+        }
+
+        (self.iterations - self.ok) as f32 / self.iterations as f32
+    }
+}
+
+// A firmware version string such as "1.7.4", as numbers that compare in
+// release order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FirmwareVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u8,
+}
+
+impl FirmwareVersion {
+    pub const fn new(major: u8, minor: u8, patch: u8) -> Self {
+        FirmwareVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    // Parses "major.minor.patch", ignoring a pre-release or build suffix on
+    // the patch number ("1.4.8-rc1", "1.7.4+custom") as seen on test and
+    // self-built firmware. None if it isn’t three dot-separated numbers.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.split(['-', '+']).next().unwrap_or(version);
+        let mut parts = version.split('.').map(|part| part.parse::<u8>().ok());
+
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Some(major)), Some(Some(minor)), Some(Some(patch)), None) => {
+                Some(FirmwareVersion::new(major, minor, patch))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Which optional parts of the protocol the running firmware has. Commands for
+// the parts it lacks either fail with Error::ErrorResponse or never get a
+// response at all, so it’s worth checking these first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capabilities {
+    pub version: FirmwareVersion,
+    // Protocol::Tls connections.
+    pub supports_tls: bool,
+    pub supports_udp: bool,
+    // Network time via the chip’s SNTP client.
+    pub supports_ntp: bool,
+    // set_pin_mode and the digital/analog writes.
+    pub supports_pins: bool,
+    // WPA2 Enterprise networks.
+    pub supports_enterprise: bool,
+    // set_client_cert.
+    pub supports_client_cert: bool,
+}
+
+impl Capabilities {
+    // The version each capability first appeared in. Everything here is
+    // derived from this table, so a new capability is one new row. The
+    // versions are the nina-fw releases that added the commands, going by
+    // its CHANGELOG:
+    //
+    // - 1.1.0: GET_TIME_CMD, backed by the ESP32’s SNTP client, and
+    //   SET_PIN_MODE / SET_DIGITAL_WRITE / SET_ANALOG_WRITE.
+    // - 1.3.0: WPA2 Enterprise, the SET_ENT_* commands.
+    // - 1.4.0: SET_CLIENT_CERT and SET_CERT_KEY, for mutual TLS.
+    const NTP_SINCE: FirmwareVersion = FirmwareVersion::new(1, 1, 0);
+    const PINS_SINCE: FirmwareVersion = FirmwareVersion::new(1, 1, 0);
+    const ENTERPRISE_SINCE: FirmwareVersion = FirmwareVersion::new(1, 3, 0);
+    const CLIENT_CERT_SINCE: FirmwareVersion = FirmwareVersion::new(1, 4, 0);
+
+    pub fn of(version: FirmwareVersion) -> Self {
+        Capabilities {
+            version,
+            // Both were in the first release.
+            supports_tls: true,
+            supports_udp: true,
+            supports_ntp: version >= Self::NTP_SINCE,
+            supports_pins: version >= Self::PINS_SINCE,
+            supports_enterprise: version >= Self::ENTERPRISE_SINCE,
+            supports_client_cert: version >= Self::CLIENT_CERT_SINCE,
+        }
+    }
+}
+
+impl<CsPin, BusyPin, Spi, SpiError, Delay, Timer> WifiNina<CsPin, BusyPin, Spi, Delay, Timer>
+where
+    BusyPin: InputPin,
+    CsPin: OutputPin,
+    Spi:
+        FullDuplex<u8, Error = SpiError> + embedded_hal::blocking::spi::Write<u8, Error = SpiError>,
+    SpiError: Debug,
+    //+ embedded_hal::blocking::spi::WriteIter<u8, Error = SpiError>,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    Timer: crate::util::timer::Timer,
+{
+    // Go/no-go check of the whole Wi-Fi module, for testing assembled boards:
+    // resets the chip, reads its firmware version and MAC address, and scans
+    // for networks. Every step is attempted even if an earlier one failed.
+    pub fn self_test<ResetPin>(
+        &mut self,
+        reset: &mut ResetPin,
+    ) -> Result<SelfTestReport, Error<SpiError>>
+    where
+        ResetPin: OutputPin,
+    {
+        Ok(SelfTestReport {
+            reset: self.reset(reset).is_ok(),
+            firmware_version: self.firmware_version::<16>().ok(),
+            mac_address: self.mac_address().ok(),
+            networks_found: self.rescan().ok(),
+        })
+    }
+
+    // Checks the SPI link by reading the firmware version over and over and
+    // counting the responses that come back wrong, for telling wiring or
+    // clock-speed problems apart from software ones when bringing up a
+    // board. A non-zero error rate on a link this simple points at the
+    // hardware. Errors from the SPI peripheral itself end the test early.
+    pub fn link_test(&mut self, iterations: u16) -> Result<LinkTestResult, Error<SpiError>> {
+        let mut result = LinkTestResult {
+            iterations,
+            ..Default::default()
+        };
+
+        let mut expected: Option<heapless::String<16>> = None;
+
+        for _ in 0..iterations {
+            match self.firmware_version::<16>() {
+                Ok(version) => match &expected {
+                    Some(expected) if *expected != version => result.mismatches += 1,
+                    Some(_) => result.ok += 1,
+                    None => {
+                        expected = Some(version);
+                        result.ok += 1;
+                    }
+                },
+
+                Err(Error::ChipSelectTimeout)
+                | Err(Error::DeviceWedged)
+                | Err(Error::ResponseTimeout) => result.timeouts += 1,
+
+                Err(err @ Error::SpiError { .. }) | Err(err @ Error::ChipSelectPinError) => {
+                    return Err(err)
+                }
+
+                Err(_) => result.framing_errors += 1,
+            }
+        }
+
+        Ok(result)
+    }
+
+    // Writes a one-line summary of the connection for logs, e.g.
+    // "status=Connected ip=192.168.1.20 rssi=-61 sockets=2". Nothing is
+    // allocated, so it can go straight to a UART or a ConnectedSocket.
+    pub fn write_status<W: core::fmt::Write>(
+        &mut self,
+        out: &mut W,
+    ) -> Result<(), Error<SpiError>> {
+        let status = self.wifi_status()?;
+        let ip = self.network_info()?.ip;
+        let rssi = self.current_rssi()?;
+        let sockets = self.open_sockets.count_ones();
+
+        write!(
+            out,
+            "status={:?} ip={}.{}.{}.{} rssi={} sockets={}",
+            status, ip[0], ip[1], ip[2], ip[3], rssi, sockets
+        )
+        .map_err(|_| Error::FormatError)
+    }
+
+    // Free heap on the ESP32, for spotting a leak before it starts refusing
+    // sockets. The firmware has no command that reports it.
+    pub fn free_heap(&mut self) -> Result<u32, Error<SpiError>> {
+        Err(Error::Unsupported)
+    }
+
+    // What the running firmware supports, going by its version.
+    pub fn capabilities(&mut self) -> Result<Capabilities, Error<SpiError>> {
+        Ok(Capabilities::of(self.firmware()?))
+    }
+
+    // The running firmware’s version. It’s only read from the chip the
+    // first time after each reset, since several commands need it to make
+    // sense of their responses.
+    pub(crate) fn firmware(&mut self) -> Result<FirmwareVersion, Error<SpiError>> {
+        if let Some(version) = self.firmware {
+            return Ok(version);
+        }
+
+        let version = FirmwareVersion::parse(&self.firmware_version::<16>()?)
+            .ok_or(Error::UnrecognizedFirmwareVersion)?;
+
+        self.firmware = Some(version);
+
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_start_at_their_release() {
+        let of = |major, minor, patch| Capabilities::of(FirmwareVersion::new(major, minor, patch));
+
+        let before = of(1, 0, 9);
+        assert!(before.supports_tls && before.supports_udp);
+        assert!(!before.supports_ntp && !before.supports_pins);
+
+        let after = of(1, 1, 0);
+        assert!(after.supports_ntp && after.supports_pins);
+        assert!(!after.supports_enterprise);
+
+        assert!(!of(1, 2, 9).supports_enterprise);
+        assert!(of(1, 3, 0).supports_enterprise);
+
+        assert!(!of(1, 3, 9).supports_client_cert);
+        assert!(of(1, 4, 0).supports_client_cert);
+
+        let current = of(1, 7, 4);
+        assert!(current.supports_ntp && current.supports_pins);
+        assert!(current.supports_enterprise && current.supports_client_cert);
+    }
+
+    #[test]
+    fn parses_firmware_versions() {
+        let parse = FirmwareVersion::parse;
+
+        assert_eq!(parse("1.4.8"), Some(FirmwareVersion::new(1, 4, 8)));
+        assert_eq!(parse("1.4.8-rc1"), Some(FirmwareVersion::new(1, 4, 8)));
+        assert_eq!(parse("1.7.4+custom"), Some(FirmwareVersion::new(1, 7, 4)));
+
+        assert_eq!(parse("1.4"), None);
+        assert_eq!(parse("1.4.8.1"), None);
+        assert_eq!(parse("1.x.8"), None);
+        assert_eq!(parse("1..8"), None);
+        assert_eq!(parse("256.0.0"), None);
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("-rc1"), None);
+    }
+}
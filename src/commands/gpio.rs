@@ -0,0 +1,108 @@
+use embedded_hal::{
+    digital::v2::{InputPin, OutputPin},
+    spi::FullDuplex,
+};
+
+use crate::{commands::*, Error, WifiNina};
+
+// The values are the Arduino ones, which the firmware passes straight on to
+// its pinMode.
+#[repr(u8)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PinMode {
+    Input = 0,
+    Output = 1,
+    InputPullup = 2,
+}
+
+// ESP32 GPIOs the red, green and blue legs of the RGB LED next to the NINA
+// module are wired to on the Arduino Nano 33 IoT and MKR WiFi 1010. Boards
+// wired differently can call analog_write with their own pins instead of
+// using set_led_rgb.
+pub const LED_RED_PIN: u8 = 26;
+pub const LED_GREEN_PIN: u8 = 25;
+pub const LED_BLUE_PIN: u8 = 27;
+
+// Access to the ESP32’s own GPIO pins, e.g. the RGB LED on Adafruit and
+// Arduino boards. Pin numbers are ESP32 GPIO numbers.
+impl<CsPin, BusyPin, Spi, SpiError, Delay, Timer> WifiNina<CsPin, BusyPin, Spi, Delay, Timer>
+where
+    BusyPin: InputPin,
+    CsPin: OutputPin,
+    Spi:
+        FullDuplex<u8, Error = SpiError> + embedded_hal::blocking::spi::Write<u8, Error = SpiError>,
+    SpiError: Debug,
+    //+ embedded_hal::blocking::spi::WriteIter<u8, Error = SpiError>,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    Timer: crate::util::timer::Timer,
+{
+    pub fn set_pin_mode(&mut self, pin: u8, mode: PinMode) -> Result<(), Error<SpiError>> {
+        self.send_and_receive(
+            NinaCommand::SetPinMode,
+            Params::of(&mut [SendParam::Byte(pin), SendParam::Byte(mode as u8)]),
+            Params::of(&mut [RecvParam::Ack]),
+        )
+    }
+
+    // The pin needs to have been made an output with set_pin_mode first.
+    pub fn digital_write(&mut self, pin: u8, value: bool) -> Result<(), Error<SpiError>> {
+        self.send_and_receive(
+            NinaCommand::SetDigitalWrite,
+            Params::of(&mut [SendParam::Byte(pin), SendParam::Byte(value as u8)]),
+            Params::of(&mut [RecvParam::Ack]),
+        )
+    }
+
+    // Outputs a PWM signal on the pin, with a duty cycle of value/255: 0 is
+    // always low, 255 always high.
+    pub fn analog_write(&mut self, pin: u8, value: u8) -> Result<(), Error<SpiError>> {
+        self.send_and_receive(
+            NinaCommand::SetAnalogWrite,
+            Params::of(&mut [SendParam::Byte(pin), SendParam::Byte(value)]),
+            Params::of(&mut [RecvParam::Ack]),
+        )
+    }
+
+    // Sets the brightness of each colour of the onboard RGB LED, 0 being
+    // off. Stops at the first write that fails.
+    pub fn set_led_rgb(&mut self, r: u8, g: u8, b: u8) -> Result<(), Error<SpiError>> {
+        self.analog_write(LED_RED_PIN, r)?;
+        self.analog_write(LED_GREEN_PIN, g)?;
+        self.analog_write(LED_BLUE_PIN, b)
+    }
+
+    // Sets several pins in a row. The firmware only knows how to set one pin
+    // per command, so this is one SetDigitalWrite each, sent back-to-back
+    // with nothing else in between. Stops at the first pin that fails.
+    pub fn digital_write_many(&mut self, writes: &[(u8, bool)]) -> Result<(), Error<SpiError>> {
+        for &(pin, value) in writes {
+            self.digital_write(pin, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+
+    #[test]
+    fn set_led_rgb_writes_each_colour_to_its_pin() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        for _ in 0..3 {
+            bus.borrow_mut()
+                .respond_with(NinaCommand::SetAnalogWrite, &[&[1]]);
+        }
+
+        wifi.set_led_rgb(10, 20, 30).unwrap();
+
+        let sent = &bus.borrow().sent;
+        // Header, pin, value and End; already a multiple of 4 bytes.
+        assert_eq!(&sent[0..8], &[0xE0, 0x52, 2, 1, 26, 1, 10, 0xEE]);
+        assert_eq!(&sent[8..16], &[0xE0, 0x52, 2, 1, 25, 1, 20, 0xEE]);
+        assert_eq!(&sent[16..24], &[0xE0, 0x52, 2, 1, 27, 1, 30, 0xEE]);
+    }
+}
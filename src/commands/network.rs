@@ -3,7 +3,14 @@ use embedded_hal::{
     spi::FullDuplex,
 };
 
-use crate::{commands::*, Error, WifiNina};
+use crate::{
+    commands::{
+        socket::{Protocol, Socket},
+        *,
+    },
+    util::dns::{self, DnsError},
+    Error, WifiNina,
+};
 
 #[derive(Debug, Default)]
 pub struct NetworkInfo {
@@ -12,7 +19,15 @@ pub struct NetworkInfo {
     pub gateway_ip: [u8; 4],
 }
 
-impl<CsPin, BusyPin, Spi, SpiError, Delay> WifiNina<CsPin, BusyPin, Spi, Delay>
+// Details of the DHCP lease behind network_info’s address.
+#[derive(Debug, Default)]
+pub struct DhcpInfo {
+    pub server_ip: [u8; 4],
+    // None if the server didn’t say.
+    pub lease_time_s: Option<u32>,
+}
+
+impl<CsPin, BusyPin, Spi, SpiError, Delay, Timer> WifiNina<CsPin, BusyPin, Spi, Delay, Timer>
 where
     BusyPin: InputPin,
     CsPin: OutputPin,
@@ -20,25 +35,100 @@ where
         FullDuplex<u8, Error = SpiError> + embedded_hal::blocking::spi::Write<u8, Error = SpiError>,
     SpiError: Debug,
     //+ embedded_hal::blocking::spi::WriteIter<u8, Error = SpiError>,
-    Delay: embedded_hal::blocking::delay::DelayMs<u16>,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    Timer: crate::util::timer::Timer,
 {
     pub fn network_info(&mut self) -> Result<NetworkInfo, Error<SpiError>> {
+        self.check_connected()?;
+
         let mut network_info: NetworkInfo = Default::default();
 
         self.send_and_receive(
             NinaCommand::GetIpAddress,
             Params::none(),
+            // Lenient about the lengths in case some future firmware sends
+            // longer addresses; we only have room for the IPv4 part.
             Params::of(&mut [
-                RecvParam::ByteArray(&mut network_info.ip),
-                RecvParam::ByteArray(&mut network_info.netmask),
-                RecvParam::ByteArray(&mut network_info.gateway_ip),
+                RecvParam::ByteArrayUpTo(&mut network_info.ip),
+                RecvParam::ByteArrayUpTo(&mut network_info.netmask),
+                RecvParam::ByteArrayUpTo(&mut network_info.gateway_ip),
             ]),
         )?;
 
         Ok(network_info)
     }
 
+    // Uses a static address rather than DHCP. The firmware applies it
+    // straight away, but some builds only keep it if it’s set before
+    // wifi_connect; set_ip_config_verified checks that it took.
+    pub fn set_ip_config(
+        &mut self,
+        ip: [u8; 4],
+        gateway: [u8; 4],
+        netmask: [u8; 4],
+    ) -> Result<(), Error<SpiError>> {
+        self.send_and_receive(
+            NinaCommand::SetIpConfig,
+            Params::of(&mut [
+                // How many of the addresses that follow to use. They’re
+                // always all sent, in this order.
+                SendParam::Byte(3),
+                SendParam::Bytes(&mut ip.iter().cloned()),
+                SendParam::Bytes(&mut gateway.iter().cloned()),
+                SendParam::Bytes(&mut netmask.iter().cloned()),
+            ]),
+            Params::of(&mut [RecvParam::Ack]),
+        )
+    }
+
+    // Sets the DNS servers resolve_host_name uses, for networks whose DHCP
+    // doesn’t hand any out (or when using set_ip_config).
+    pub fn set_dns_servers(
+        &mut self,
+        primary: [u8; 4],
+        secondary: Option<[u8; 4]>,
+    ) -> Result<(), Error<SpiError>> {
+        // The firmware always takes two addresses, and a count of how many of
+        // them to use.
+        let (count, secondary) = match secondary {
+            Some(secondary) => (2, secondary),
+            None => (1, [0; 4]),
+        };
+
+        self.send_and_receive(
+            NinaCommand::SetDnsConfig,
+            Params::of(&mut [
+                SendParam::Byte(count),
+                SendParam::Bytes(&mut primary.iter().cloned()),
+                SendParam::Bytes(&mut secondary.iter().cloned()),
+            ]),
+            Params::of(&mut [RecvParam::Ack]),
+        )
+    }
+
+    // Like set_ip_config, but then reads the config back and fails with
+    // Error::ConfigMismatch if the firmware didn’t apply it.
+    pub fn set_ip_config_verified(
+        &mut self,
+        ip: [u8; 4],
+        gateway: [u8; 4],
+        netmask: [u8; 4],
+    ) -> Result<NetworkInfo, Error<SpiError>> {
+        self.set_ip_config(ip, gateway, netmask)?;
+
+        let info = self.network_info()?;
+
+        if info.ip != ip || info.gateway_ip != gateway || info.netmask != netmask {
+            return Err(Error::ConfigMismatch);
+        }
+
+        Ok(info)
+    }
+
     pub fn resolve_host_name(&mut self, name: &str) -> Result<[u8; 4], Error<SpiError>> {
+        self.check_connected()?;
+
         let mut ip = [0u8; 4];
 
         self.send_and_receive(
@@ -55,4 +145,193 @@ where
 
         Ok(ip)
     }
+
+    // Looks up the name by asking the DNS server directly over UDP, rather
+    // than through the firmware’s resolver, for firmware whose
+    // resolve_host_name is broken. Waits for the answer for up to the
+    // config’s read_timeout_ms. Fails with Error::HostNotFound if the server
+    // has no IPv4 address for the name.
+    pub fn resolve_via_udp(
+        &mut self,
+        server: [u8; 4],
+        name: &str,
+    ) -> Result<[u8; 4], Error<SpiError>> {
+        self.check_connected()?;
+
+        // Any id will do, since only one query is ever in flight.
+        const QUERY_ID: u16 = 0x4E49;
+
+        let mut query = [0u8; dns::MAX_QUERY_LEN];
+        let query_len =
+            dns::encode_query(QUERY_ID, name, &mut query).ok_or(Error::InvalidHostname)?;
+
+        let socket = self.socket_new()?;
+
+        let mut response = [0u8; 512];
+        let exchanged = self.dns_exchange(&socket, server, &query[..query_len], &mut response);
+        let closed = self.socket_close(&socket);
+
+        let response_len = exchanged?;
+        closed?;
+
+        dns::parse_response(QUERY_ID, &response[..response_len]).map_err(|err| match err {
+            DnsError::NotFound => Error::HostNotFound,
+            DnsError::Malformed => Error::InvalidDnsResponse,
+        })
+    }
+
+    fn dns_exchange(
+        &mut self,
+        socket: &Socket,
+        server: [u8; 4],
+        query: &[u8],
+        response: &mut [u8],
+    ) -> Result<usize, Error<SpiError>> {
+        self.udp_send(socket, server, 53, query)?;
+
+        for _ in 0..Self::poll_count(self.config.read_timeout_ms) {
+            match self.udp_read(socket, response) {
+                Ok(read) => return Ok(read),
+                Err(nb::Error::Other(err)) => return Err(err),
+                Err(nb::Error::WouldBlock) => self.delay.delay_ms(Self::POLL_INTERVAL_MS),
+            }
+        }
+
+        Err(Error::SocketTimeout)
+    }
+
+    // The firmware has no command for the DHCP lease; all it passes on is
+    // the address, netmask and gateway in network_info.
+    pub fn dhcp_info(&mut self) -> Result<DhcpInfo, Error<SpiError>> {
+        Err(Error::Unsupported)
+    }
+
+    // Answers mDNS queries for `hostname`.local. The firmware has no mDNS
+    // commands (it doesn’t build in the ESP32’s responder), for this or
+    // mdns_add_service.
+    pub fn mdns_begin(&mut self, _hostname: &str) -> Result<(), Error<SpiError>> {
+        Err(Error::Unsupported)
+    }
+
+    // Advertises a service, e.g. `_http` over Protocol::TCP, on the port.
+    pub fn mdns_add_service(
+        &mut self,
+        _name: &str,
+        _protocol: Protocol,
+        _port: u16,
+    ) -> Result<(), Error<SpiError>> {
+        Err(Error::Unsupported)
+    }
+
+    // The chip’s clock, in seconds since the Unix epoch. The firmware sets it
+    // with SNTP once connected; this is what it checks TLS certificates’
+    // validity against.
+    pub fn get_time(&mut self) -> Result<u32, Error<SpiError>> {
+        let mut time: u32 = 0;
+
+        self.send_and_receive(
+            NinaCommand::GetTime,
+            Params::none(),
+            // Copied out of a time_t as-is, so little-endian.
+            Params::of(&mut [RecvParam::LEDWord(&mut time)]),
+        )?;
+
+        Ok(time)
+    }
+
+    // Sets the chip’s clock, for devices that know the time from a GPS or
+    // RTC and can’t reach an NTP server. The firmware has no set-time
+    // command, only GetTime; its clock is set by SNTP alone.
+    pub fn set_time(&mut self, _epoch: u32) -> Result<(), Error<SpiError>> {
+        Err(Error::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{commands::NinaCommand, mock, Error};
+
+    #[test]
+    fn set_ip_config_sends_ip_then_gateway_then_netmask() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SetIpConfig, &[&[1]]);
+
+        wifi.set_ip_config([10, 0, 0, 5], [10, 0, 0, 1], [255, 0, 0, 0])
+            .unwrap();
+
+        assert_eq!(
+            &bus.borrow().sent[..20],
+            &[
+                0xE0, 0x14, 4, //
+                1, 3, //
+                4, 10, 0, 0, 5, //
+                4, 10, 0, 0, 1, //
+                4, 255, 0, 0, 0,
+            ]
+        );
+    }
+
+    #[test]
+    fn set_dns_servers_always_sends_two_addresses() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SetDnsConfig, &[&[1]]);
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SetDnsConfig, &[&[1]]);
+
+        wifi.set_dns_servers([1, 1, 1, 1], Some([8, 8, 8, 8]))
+            .unwrap();
+        wifi.set_dns_servers([9, 9, 9, 9], None).unwrap();
+
+        let sent = &bus.borrow().sent;
+        assert_eq!(
+            &sent[..16],
+            &[
+                0xE0, 0x15, 3, //
+                1, 2, //
+                4, 1, 1, 1, 1, //
+                4, 8, 8, 8, 8, //
+                0xEE,
+            ]
+        );
+        // Only the first counts, but the second is still there.
+        assert_eq!(
+            &sent[16..],
+            &[
+                0xE0, 0x15, 3, //
+                1, 1, //
+                4, 9, 9, 9, 9, //
+                4, 0, 0, 0, 0, //
+                0xEE,
+            ]
+        );
+    }
+
+    #[test]
+    fn set_ip_config_verified_reports_a_config_that_didnt_take() {
+        let (mut wifi, bus) = mock::wifi_nina();
+
+        let ip = [192, 168, 1, 50];
+        let gateway = [192, 168, 1, 1];
+        let netmask = [255, 255, 255, 0];
+
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SetIpConfig, &[&[1]]);
+        bus.borrow_mut().respond_with(
+            NinaCommand::GetIpAddress,
+            &[&[192, 168, 1, 73], &netmask, &gateway],
+        );
+
+        let result = wifi.set_ip_config_verified(ip, gateway, netmask);
+        assert!(matches!(result, Err(Error::ConfigMismatch)));
+
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SetIpConfig, &[&[1]]);
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetIpAddress, &[&ip, &netmask, &gateway]);
+
+        let info = wifi.set_ip_config_verified(ip, gateway, netmask).unwrap();
+        assert_eq!(info.ip, ip);
+    }
 }
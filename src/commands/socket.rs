@@ -14,9 +14,23 @@ use numtoa::NumToA;
 #[cfg(feature = "genio-traits")]
 use void;
 
-use crate::{commands::*, Error, WifiNina};
+use crate::{
+    commands::*,
+    metrics::Event,
+    util::{gather::Gather, timer::PollCounter},
+    Error, WifiNina,
+};
+
+// Most data that socket_write will hand to the chip in one SendDataTcp.
+// The firmware receives each command into a 4092-byte SPI buffer, which also
+// has to fit the command framing, so this leaves some headroom below that.
+// Larger writes are split into chunks of this size.
+pub const MAX_SEND_CHUNK: usize = 4000;
+
+// Number of sockets the firmware has (its MAX_SOCK_NUM).
+pub const MAX_SOCKETS: usize = 10;
 
-impl<CsPin, BusyPin, Spi, SpiError, Delay> WifiNina<CsPin, BusyPin, Spi, Delay>
+impl<CsPin, BusyPin, Spi, SpiError, Delay, Timer> WifiNina<CsPin, BusyPin, Spi, Delay, Timer>
 where
     BusyPin: InputPin,
     CsPin: OutputPin,
@@ -24,8 +38,18 @@ where
         FullDuplex<u8, Error = SpiError> + embedded_hal::blocking::spi::Write<u8, Error = SpiError>,
     SpiError: Debug,
     //+ embedded_hal::blocking::spi::WriteIter<u8, Error = SpiError>,
-    Delay: embedded_hal::blocking::delay::DelayMs<u16>,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    Timer: crate::util::timer::Timer,
 {
+    // How often the waiting socket methods check on the chip.
+    pub(crate) const POLL_INTERVAL_MS: u16 = 10;
+
+    // Number of polls that add up to the given timeout, at least one.
+    pub(crate) fn poll_count(timeout_ms: u16) -> u16 {
+        core::cmp::max(timeout_ms / Self::POLL_INTERVAL_MS, 1)
+    }
+
     pub fn socket_new(&mut self) -> Result<Socket, Error<SpiError>> {
         let mut socket = InvalidSocket::new();
 
@@ -35,7 +59,61 @@ where
             Params::of(&mut [RecvParam::Socket(&mut socket)]),
         )?;
 
-        Ok(socket.try_into().map_err(|_| Error::NoSocketAvailable)?)
+        let mut socket: Socket = socket.try_into().map_err(Error::NoSocketAvailable)?;
+
+        self.stamp_generation(&mut socket);
+        self.mark_socket_open(&socket, true);
+
+        Ok(socket)
+    }
+
+    fn mark_socket_open(&mut self, socket: &Socket, open: bool) {
+        if (socket.num() as usize) < MAX_SOCKETS {
+            let bit = 1 << socket.num();
+
+            if open {
+                self.open_sockets |= bit;
+            } else {
+                self.open_sockets &= !bit;
+            }
+        }
+    }
+
+    // Records that the chip has just handed out the socket’s number, making
+    // any Socket already holding it stale.
+    fn stamp_generation(&mut self, socket: &mut Socket) {
+        if let Some(generation) = self.socket_generations.get_mut(socket.num() as usize) {
+            *generation = generation.wrapping_add(1);
+            socket.generation = Some(*generation);
+        }
+    }
+
+    // The firmware hands out socket numbers without any notion of who owns
+    // them, and socket_status only reports the TCP state of whatever is using
+    // the number now, so the chip can’t tell us that a number we’re holding
+    // has since been given to someone else. Instead we count, per number, how
+    // many times it has been handed out (by socket_new, or to a client
+    // accepted by a server socket) and the Socket remembers the count it was
+    // made with. Resetting the chip counts as handing out every number again.
+    // If they disagree, the number was reused after this Socket was made.
+    //
+    // What this can’t catch: Sockets from Socket::new have no count and are
+    // never rejected, and the chip reusing a number by itself (e.g. after a
+    // crash and reboot we didn’t cause) goes unnoticed until socket_status
+    // or a read shows the connection isn’t ours.
+    fn check_socket_generation(&self, socket: &Socket) -> Result<(), Error<SpiError>> {
+        let current = self.socket_generations.get(socket.num() as usize);
+
+        match (socket.generation, current) {
+            (Some(generation), Some(&current)) if generation != current => Err(Error::SocketReused),
+            _ => Ok(()),
+        }
+    }
+
+    // Whether the socket’s number still belongs to it, i.e. whether
+    // check_socket_generation would let it through.
+    pub(crate) fn socket_is_current(&self, socket: &Socket) -> bool {
+        self.check_socket_generation(socket).is_ok()
     }
 
     pub fn socket_status(&mut self, socket: &Socket) -> Result<SocketStatus, Error<SpiError>> {
@@ -50,6 +128,23 @@ where
         Ok(status.into())
     }
 
+    // socket_status for each of the sockets, into the matching slot of out
+    // (as far as both go). The firmware answers one socket per command and
+    // needs CS released between each request and its response, so this saves
+    // nothing on the bus, only the bookkeeping of a loop.
+    pub fn socket_statuses(
+        &mut self,
+        sockets: &[&Socket],
+        out: &mut [SocketStatus],
+    ) -> Result<(), Error<SpiError>> {
+        for (socket, status) in sockets.iter().zip(out.iter_mut()) {
+            *status = self.socket_status(socket)?;
+        }
+
+        Ok(())
+    }
+
+    // Waits up to the config’s connect_timeout_ms for the connection.
     pub fn socket_open(
         &mut self,
         socket: &'_ Socket,
@@ -57,6 +152,77 @@ where
         destination: Destination,
         port: u16,
     ) -> Result<SocketStatus, Error<SpiError>> {
+        let timeout_ms = self.config.connect_timeout_ms;
+
+        self.socket_open_with_timeout(socket, protocol, destination, port, timeout_ms)
+    }
+
+    pub fn socket_open_with_timeout(
+        &mut self,
+        socket: &'_ Socket,
+        protocol: Protocol,
+        destination: Destination,
+        port: u16,
+        timeout_ms: u16,
+    ) -> Result<SocketStatus, Error<SpiError>> {
+        self.socket_begin_open(socket, protocol, destination, port)?;
+        self.wait_for_open(socket, timeout_ms, &mut || false)
+    }
+
+    // Like socket_open, but checks `cancel` between polls and gives up with
+    // Error::Cancelled as soon as it returns true. The socket is left as it
+    // was; close it to abandon the connection attempt.
+    pub fn socket_open_cancellable(
+        &mut self,
+        socket: &'_ Socket,
+        protocol: Protocol,
+        destination: Destination,
+        port: u16,
+        cancel: &mut dyn FnMut() -> bool,
+    ) -> Result<SocketStatus, Error<SpiError>> {
+        let timeout_ms = self.config.connect_timeout_ms;
+
+        self.socket_begin_open(socket, protocol, destination, port)?;
+        self.wait_for_open(socket, timeout_ms, cancel)
+    }
+
+    fn wait_for_open(
+        &mut self,
+        socket: &Socket,
+        timeout_ms: u16,
+        cancel: &mut dyn FnMut() -> bool,
+    ) -> Result<SocketStatus, Error<SpiError>> {
+        let mut last_status = SocketStatus::UnknownStatus;
+
+        for _ in 0..Self::poll_count(timeout_ms) {
+            last_status = self.socket_status(socket)?;
+
+            if last_status == SocketStatus::Established {
+                return Ok(SocketStatus::Established);
+            }
+
+            if cancel() {
+                return Err(Error::Cancelled);
+            }
+
+            self.delay.delay_ms(Self::POLL_INTERVAL_MS);
+        }
+
+        Err(Error::SocketConnectionFailed(last_status))
+    }
+
+    // Starts opening the socket without waiting for the connection to be
+    // established. Drive it with socket_poll_open, or close the socket to
+    // give up on it.
+    pub fn socket_begin_open(
+        &mut self,
+        socket: &'_ Socket,
+        protocol: Protocol,
+        destination: Destination,
+        port: u16,
+    ) -> Result<(), Error<SpiError>> {
+        self.check_connected()?;
+
         let mut result: Option<u8> = None;
 
         match destination {
@@ -81,26 +247,39 @@ where
                 ]),
                 Params::of(&mut [RecvParam::OptionalByte(&mut result)]),
             )?,
+            Destination::ResolvedHostname { name, ip } => self.send_and_receive(
+                NinaCommand::StartClientTcp,
+                Params::of(&mut [
+                    SendParam::Bytes(&mut name.bytes()),
+                    SendParam::Bytes(&mut ip.iter().cloned()),
+                    SendParam::Word(port),
+                    SendParam::Byte(socket.num()),
+                    SendParam::Byte(protocol.into()),
+                ]),
+                Params::of(&mut [RecvParam::OptionalByte(&mut result)]),
+            )?,
         }
 
-        if let None = result {
+        if result.is_none() {
             return Err(Error::SocketConnectionFailed(SocketStatus::UnknownStatus));
         }
 
-        let mut last_status = SocketStatus::UnknownStatus;
-
-        // Wait 3 seconds for the connection.
-        for _ in 0..300 {
-            last_status = self.socket_status(&socket)?;
+        // Reopening a number we already had (reconnect_socket, the pool)
+        // doesn’t go through socket_new.
+        self.mark_socket_open(socket, true);
 
-            if last_status == SocketStatus::Established {
-                return Ok(SocketStatus::Established);
-            }
+        Ok(())
+    }
 
-            self.delay.delay_ms(10);
+    // Checks on a socket opened with socket_begin_open.
+    pub fn socket_poll_open(
+        &mut self,
+        socket: &Socket,
+    ) -> Result<SocketStatus, nb::Error<Error<SpiError>>> {
+        match self.socket_status(socket)? {
+            SocketStatus::Established => Ok(SocketStatus::Established),
+            _ => Err(nb::Error::WouldBlock),
         }
-
-        Err(Error::SocketConnectionFailed(last_status))
     }
 
     // Closes the socket.
@@ -113,7 +292,61 @@ where
             NinaCommand::StopClientTcp,
             Params::of(&mut [SendParam::Byte(socket.num())]),
             Params::of(&mut [RecvParam::Ack]),
-        )
+        )?;
+
+        self.mark_socket_open(socket, false);
+        self.read_ahead.clear(socket);
+
+        Ok(())
+    }
+
+    // Whether everything written to the socket has been sent on to the
+    // network, rather than sitting in the chip’s buffers. Stock nina-fw
+    // doesn’t track this and always answers yes, so this only means
+    // something on firmware that implements DataSentTcp.
+    pub fn socket_data_sent(&mut self, socket: &Socket) -> Result<bool, Error<SpiError>> {
+        let mut sent: u8 = 0;
+
+        self.send_and_receive(
+            NinaCommand::DataSentTcp,
+            Params::of(&mut [SendParam::Byte(socket.num())]),
+            Params::of(&mut [RecvParam::Byte(&mut sent)]),
+        )?;
+
+        Ok(sent != 0)
+    }
+
+    // How many more bytes the chip could take for the socket right now. The
+    // firmware has no command for this, only DataSentTcp for whether it has
+    // all been sent (socket_data_sent).
+    pub fn socket_tx_free(&mut self, _socket: &Socket) -> Result<u16, Error<SpiError>> {
+        Err(Error::Unsupported)
+    }
+
+    // Waits until socket_data_sent reports every open socket as sent, or
+    // fails with Error::SocketTimeout after timeout_ms. Stock nina-fw always
+    // reports sent, so there this returns on the first pass and doesn’t
+    // guarantee that anything has reached the network; don’t rely on it to
+    // keep data from being lost to a reset or sleep.
+    pub fn flush_all(&mut self, timeout_ms: u16) -> Result<(), Error<SpiError>> {
+        // Sockets that still have something to send.
+        let mut pending = self.open_sockets;
+
+        for _ in 0..Self::poll_count(timeout_ms) {
+            for num in 0..MAX_SOCKETS as u8 {
+                if pending & (1 << num) != 0 && self.socket_data_sent(&Socket::new(num))? {
+                    pending &= !(1 << num);
+                }
+            }
+
+            if pending == 0 {
+                return Ok(());
+            }
+
+            self.delay.delay_ms(Self::POLL_INTERVAL_MS);
+        }
+
+        Err(Error::SocketTimeout)
     }
 
     pub fn connect(
@@ -121,14 +354,86 @@ where
         protocol: Protocol,
         destination: Destination,
         port: u16,
-    ) -> Result<ConnectedSocket<'_, CsPin, BusyPin, Spi, SpiError, Delay>, Error<SpiError>> {
+    ) -> Result<ConnectedSocket<'_, CsPin, BusyPin, Spi, SpiError, Delay, Timer>, Error<SpiError>>
+    {
+        let timeout_ms = self.config.connect_timeout_ms;
+
+        self.connect_with_timeout(protocol, destination, port, timeout_ms)
+    }
+
+    // Like connect, but with its own timeout in place of the config’s
+    // connect_timeout_ms.
+    pub fn connect_with_timeout(
+        &mut self,
+        protocol: Protocol,
+        destination: Destination,
+        port: u16,
+        timeout_ms: u16,
+    ) -> Result<ConnectedSocket<'_, CsPin, BusyPin, Spi, SpiError, Delay, Timer>, Error<SpiError>>
+    {
         let socket = self.socket_new()?;
 
+        self.socket_open_with_timeout(&socket, protocol, destination, port, timeout_ms)?;
+
+        Ok(ConnectedSocket::new(self, socket))
+    }
+
+    // Opens a new connection on a socket number we already hold, rather than
+    // asking the chip for a fresh one with GetSocket. Pairs with
+    // ConnectedSocket::into_socket so that a client that connects and
+    // disconnects repeatedly doesn’t churn through the chip’s socket pool.
+    //
+    // Closing the socket frees its number on the chip, so socket_new may have
+    // handed it out again since; that fails with Error::SocketReused rather
+    // than taking over the other connection.
+    pub fn reconnect_socket(
+        &mut self,
+        socket: Socket,
+        protocol: Protocol,
+        destination: Destination,
+        port: u16,
+    ) -> Result<ConnectedSocket<'_, CsPin, BusyPin, Spi, SpiError, Delay, Timer>, Error<SpiError>>
+    {
+        self.check_socket_generation(&socket)?;
+
+        // socket_close cleared the socket’s open bit, and this puts it back.
         self.socket_open(&socket, protocol, destination, port)?;
+        self.count(Event::Reconnect);
 
         Ok(ConnectedSocket::new(self, socket))
     }
 
+    // Opens and immediately closes a throwaway TCP connection, so that the
+    // chip has the route (and the gateway’s ARP entry) to hand by the time a
+    // latency-sensitive request needs it. This is a best-effort nudge: it
+    // returns whatever went wrong opening the connection, but the socket is
+    // closed either way.
+    pub fn warm_up(&mut self, destination: Destination, port: u16) -> Result<(), Error<SpiError>> {
+        let socket = self.socket_new()?;
+
+        let opened = self.socket_open(&socket, Protocol::TCP, destination, port);
+        let closed = self.socket_close(&socket);
+
+        opened?;
+        closed
+    }
+
+    // Like server, but wraps the socket in a Listener that accepts clients
+    // and stops listening when dropped.
+    pub fn listen(
+        &mut self,
+        protocol: Protocol,
+        port: u16,
+    ) -> Result<Listener<'_, CsPin, BusyPin, Spi, SpiError, Delay, Timer>, Error<SpiError>> {
+        let socket = self.server(protocol, port)?;
+
+        Ok(Listener {
+            wifi: self,
+            socket,
+            port,
+        })
+    }
+
     pub fn server(&mut self, protocol: Protocol, port: u16) -> Result<Socket, Error<SpiError>> {
         let server_socket = self.socket_new()?;
         let mut result: Option<u8> = None;
@@ -142,53 +447,525 @@ where
             Params::of(&mut [RecvParam::OptionalByte(&mut result)]),
         )?;
 
-        Ok(server_socket)
+        // A listening socket has nothing of its own to send, so flush_all
+        // shouldn’t wait on it.
+        self.mark_socket_open(&server_socket, false);
+
+        Ok(server_socket)
+    }
+
+    pub fn select_available(
+        &mut self,
+        server_socket: &Socket,
+    ) -> Result<ConnectedSocket<'_, CsPin, BusyPin, Spi, SpiError, Delay, Timer>, Error<SpiError>>
+    {
+        let socket = self.poll_accept(server_socket)?;
+
+        Ok(ConnectedSocket::new(self, socket))
+    }
+
+    // Waits for a client to connect to a server socket. `None` waits for the
+    // config’s read_timeout_ms. Fails with Error::SocketTimeout if nobody
+    // connects in time.
+    pub fn accept(
+        &mut self,
+        server_socket: &Socket,
+        timeout_ms: Option<u16>,
+    ) -> Result<ConnectedSocket<'_, CsPin, BusyPin, Spi, SpiError, Delay, Timer>, Error<SpiError>>
+    {
+        let timeout_ms = timeout_ms.unwrap_or(self.config.read_timeout_ms);
+
+        for _ in 0..Self::poll_count(timeout_ms) {
+            match self.poll_accept(server_socket) {
+                Ok(socket) => return Ok(ConnectedSocket::new(self, socket)),
+                Err(Error::NoSocketAvailable(_)) => {}
+                Err(err) => return Err(err),
+            }
+
+            self.delay.delay_ms(Self::POLL_INTERVAL_MS);
+        }
+
+        Err(Error::SocketTimeout)
+    }
+
+    // Error::NoSocketAvailable if there’s no client waiting.
+    fn poll_accept(&mut self, server_socket: &Socket) -> Result<Socket, Error<SpiError>> {
+        let mut client_socket: u16 = 0;
+
+        self.send_and_receive(
+            NinaCommand::AvailableDataTcp,
+            Params::of(&mut [SendParam::Byte(server_socket.num())]),
+            Params::of(&mut [RecvParam::LEWord(&mut client_socket)]),
+        )?;
+
+        let mut socket: Socket = InvalidSocket::from(client_socket as u8)
+            .try_into()
+            .map_err(Error::NoSocketAvailable)?;
+
+        self.stamp_generation(&mut socket);
+        self.mark_socket_open(&socket, true);
+
+        Ok(socket)
+    }
+
+    // Returns how many bytes the chip accepted, which may be fewer than were
+    // given if its send buffer is full. Writes larger than MAX_SEND_CHUNK
+    // are sent as several commands.
+    pub fn socket_write(
+        &mut self,
+        socket: &Socket,
+        bytes: &mut dyn ExactSizeIterator<Item = u8>,
+    ) -> Result<usize, Error<SpiError>> {
+        self.check_socket_generation(socket)?;
+
+        let mut total: usize = 0;
+
+        while bytes.len() > 0 {
+            let chunk_len = core::cmp::min(bytes.len(), MAX_SEND_CHUNK);
+            let mut chunk = (&mut *bytes).take(chunk_len);
+            let mut written = 0u16;
+
+            self.send_and_receive(
+                NinaCommand::SendDataTcp,
+                Params::with_16_bit_length(&mut [
+                    SendParam::Byte(socket.num()),
+                    SendParam::Bytes(&mut chunk),
+                ]),
+                // Yes, this comes back in little-endian rather than in network order.
+                Params::of(&mut [RecvParam::LEWord(&mut written)]),
+            )?;
+
+            total += written as usize;
+
+            if (written as usize) < chunk_len {
+                break;
+            }
+        }
+
+        Ok(total)
+    }
+
+    // Writes several buffers as if they were one, e.g. a request line,
+    // headers and body, without copying them together first. They go out in
+    // a single SendDataTcp (as long as they add up to no more than
+    // MAX_SEND_CHUNK) so the peer sees them in one segment. Returns how many
+    // bytes the chip accepted, like socket_write.
+    pub fn socket_write_vectored(
+        &mut self,
+        socket: &Socket,
+        bufs: &[&[u8]],
+    ) -> Result<usize, Error<SpiError>> {
+        self.socket_write(socket, &mut Gather::new(bufs))
+    }
+
+    // Writes all of data, waiting whenever the chip’s buffer for the socket
+    // is full. Fails with Error::SocketTimeout if the chip takes nothing for
+    // the config’s read_timeout_ms.
+    pub fn socket_write_all(
+        &mut self,
+        socket: &Socket,
+        data: &[u8],
+    ) -> Result<(), Error<SpiError>> {
+        let mut sent = 0;
+        let mut stalls = 0;
+
+        while sent < data.len() {
+            match self.socket_write(socket, &mut data[sent..].iter().cloned())? {
+                0 => {
+                    stalls += 1;
+
+                    if stalls >= Self::poll_count(self.config.read_timeout_ms) {
+                        return Err(Error::SocketTimeout);
+                    }
+
+                    self.count(Event::Retry);
+                    self.delay.delay_ms(Self::POLL_INTERVAL_MS);
+                }
+                written => {
+                    sent += written;
+                    stalls = 0;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn socket_read(
+        &mut self,
+        socket: &Socket,
+        buf: &mut [u8],
+    ) -> Result<usize, nb::Error<Error<SpiError>>> {
+        self.check_socket_generation(socket)?;
+
+        let buffered = self.read_ahead.take(socket, buf);
+
+        if buffered > 0 {
+            return Ok(buffered);
+        }
+
+        let mut available: u16 = 0;
+
+        self.send_and_receive(
+            NinaCommand::AvailableDataTcp,
+            Params::of(&mut [SendParam::Byte(socket.num())]),
+            Params::of(&mut [RecvParam::LEWord(&mut available)]),
+        )
+        .map_err(nb::Error::Other)?;
+
+        if available == 0 {
+            return match self.socket_status(socket)? {
+                SocketStatus::Closed => Ok(0),
+                _ => Err(nb::Error::WouldBlock),
+            };
+        }
+
+        let req_size = core::cmp::min(available, buf.len() as u16);
+
+        let mut read: usize = 0;
+
+        self.send_and_receive(
+            NinaCommand::GetDatabufTcp,
+            Params::with_16_bit_length(&mut [
+                SendParam::Byte(socket.num()),
+                SendParam::LEWord(req_size),
+            ]),
+            Params::with_16_bit_length(&mut [RecvParam::Buffer(buf, &mut read)]),
+        )
+        .map_err(nb::Error::Other)?;
+
+        Ok(read)
+    }
+
+    // UDP sockets send a datagram in two steps: the data is staged with
+    // udp_insert (which can be called more than once to append to it) and
+    // then sent with udp_send_staged.
+    pub(crate) fn udp_insert(
+        &mut self,
+        socket: &Socket,
+        data: &[u8],
+    ) -> Result<(), Error<SpiError>> {
+        self.check_socket_generation(socket)?;
+
+        self.send_and_receive(
+            NinaCommand::InsertDataBuf,
+            Params::with_16_bit_length(&mut [
+                SendParam::Byte(socket.num()),
+                SendParam::Bytes(&mut data.iter().cloned()),
+            ]),
+            Params::of(&mut [RecvParam::Ack]),
+        )
+    }
+
+    pub(crate) fn udp_send_staged(&mut self, socket: &Socket) -> Result<(), Error<SpiError>> {
+        self.send_and_receive(
+            NinaCommand::SendUdpData,
+            Params::of(&mut [SendParam::Byte(socket.num())]),
+            Params::of(&mut [RecvParam::Ack]),
+        )
+    }
+
+    // Sends the data to dest:port as a single datagram from the UDP socket.
+    // The socket doesn’t need opening first: each datagram points it at its
+    // own destination. Unlike socket_open this leaves the socket out of the
+    // open sockets that flush_all waits on, since there’s no stream to flush.
+    pub fn udp_send(
+        &mut self,
+        socket: &Socket,
+        dest: [u8; 4],
+        port: u16,
+        data: &[u8],
+    ) -> Result<(), Error<SpiError>> {
+        self.check_socket_generation(socket)?;
+
+        let mut result: Option<u8> = None;
+
+        self.send_and_receive(
+            NinaCommand::StartClientTcp,
+            Params::of(&mut [
+                SendParam::Bytes(&mut dest.iter().cloned()),
+                SendParam::Word(port),
+                SendParam::Byte(socket.num()),
+                SendParam::Byte(Protocol::UDP.into()),
+            ]),
+            Params::of(&mut [RecvParam::OptionalByte(&mut result)]),
+        )?;
+
+        if result.is_none() {
+            return Err(Error::SocketConnectionFailed(SocketStatus::UnknownStatus));
+        }
+
+        self.udp_insert(socket, data)?;
+        self.udp_send_staged(socket)
+    }
+
+    // Reads from the datagram the chip has received for the UDP socket. A
+    // UDP socket has no connection to report as closed (the chip says it’s
+    // Closed throughout), so unlike socket_read this never returns Ok(0) for
+    // a closed socket, only WouldBlock until something arrives.
+    pub(crate) fn udp_read(
+        &mut self,
+        socket: &Socket,
+        buf: &mut [u8],
+    ) -> Result<usize, nb::Error<Error<SpiError>>> {
+        self.check_socket_generation(socket)?;
+
+        let mut available: u16 = 0;
+
+        self.send_and_receive(
+            NinaCommand::AvailableDataTcp,
+            Params::of(&mut [SendParam::Byte(socket.num())]),
+            Params::of(&mut [RecvParam::LEWord(&mut available)]),
+        )
+        .map_err(nb::Error::Other)?;
+
+        if available == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let req_size = core::cmp::min(available, buf.len() as u16);
+
+        let mut read: usize = 0;
+
+        self.send_and_receive(
+            NinaCommand::GetDatabufTcp,
+            Params::with_16_bit_length(&mut [
+                SendParam::Byte(socket.num()),
+                SendParam::LEWord(req_size),
+            ]),
+            Params::with_16_bit_length(&mut [RecvParam::Buffer(buf, &mut read)]),
+        )
+        .map_err(nb::Error::Other)?;
+
+        Ok(read)
     }
 
-    pub fn select_available(
+    // Reads the next datagram the UDP socket has received into buf, along
+    // with the address and port it came from. A datagram longer than buf is
+    // cut short; the rest of it is returned by the next call, with the same
+    // sender. WouldBlock until something arrives, like socket_read.
+    pub fn udp_recv(
         &mut self,
-        server_socket: &Socket,
-    ) -> Result<ConnectedSocket<'_, CsPin, BusyPin, Spi, SpiError, Delay>, Error<SpiError>> {
-        let mut client_socket: u16 = 0;
+        socket: &Socket,
+        buf: &mut [u8],
+    ) -> Result<(usize, [u8; 4], u16), nb::Error<Error<SpiError>>> {
+        let read = self.udp_read(socket, buf)?;
+        let (ip, port) = self.peer_address(socket).map_err(nb::Error::Other)?;
+
+        Ok((read, ip, port))
+    }
+
+    // Address and port of the other end of the socket: the client of a
+    // connection accepted by a server socket, or the sender of the last
+    // datagram a UDP socket received.
+    pub fn peer_address(&mut self, socket: &Socket) -> Result<([u8; 4], u16), Error<SpiError>> {
+        let mut ip = [0u8; 4];
+        let mut port: u16 = 0;
 
         self.send_and_receive(
-            NinaCommand::AvailableDataTcp,
-            Params::of(&mut [SendParam::Byte(server_socket.num())]),
-            Params::of(&mut [RecvParam::LEWord(&mut client_socket)]),
+            NinaCommand::GetRemoteData,
+            Params::of(&mut [SendParam::Byte(socket.num())]),
+            Params::of(&mut [RecvParam::ByteArray(&mut ip), RecvParam::Word(&mut port)]),
         )?;
 
-        let socket: Socket = InvalidSocket::from(client_socket as u8)
-            .try_into()
-            .map_err(|_| Error::NoSocketAvailable)?;
+        Ok((ip, port))
+    }
 
-        Ok(ConnectedSocket::new(self, Socket::new(client_socket as u8)))
+    // Reads and throws away whatever the chip has buffered for the socket,
+    // returning how many bytes that was. Useful before reusing a socket whose
+    // last response wasn’t read to the end. Only drops what has already
+    // arrived; anything still in flight will show up afterwards.
+    pub fn socket_drain(&mut self, socket: &Socket) -> Result<usize, Error<SpiError>> {
+        let mut buf = [0u8; 64];
+        let mut total: usize = self.read_ahead.clear(socket);
+
+        loop {
+            match self.socket_read(socket, &mut buf) {
+                Ok(0) | Err(nb::Error::WouldBlock) => return Ok(total),
+                Ok(read) => total += read,
+                Err(nb::Error::Other(err)) => return Err(err),
+            }
+        }
     }
 
-    pub fn socket_write(
+    // Like socket_read, but waits for data to arrive rather than returning
+    // WouldBlock. `None` waits for the config’s read_timeout_ms. Fails with
+    // Error::SocketTimeout if nothing arrives in time. Returns 0 once the
+    // socket has closed.
+    pub fn socket_read_timeout(
         &mut self,
         socket: &Socket,
-        bytes: &mut dyn ExactSizeIterator<Item = u8>,
+        buf: &mut [u8],
+        timeout_ms: Option<u16>,
     ) -> Result<usize, Error<SpiError>> {
-        let mut written = 0u16;
+        let timeout_ms = timeout_ms.unwrap_or(self.config.read_timeout_ms);
 
-        self.send_and_receive(
-            NinaCommand::SendDataTcp,
-            Params::with_16_bit_length(&mut [
-                SendParam::Byte(socket.num()),
-                SendParam::Bytes(bytes),
-            ]),
-            // Yes, this comes back in little-endian rather than in network order.
-            Params::of(&mut [RecvParam::LEWord(&mut written)]),
-        )?;
+        for _ in 0..Self::poll_count(timeout_ms) {
+            match self.socket_read(socket, buf) {
+                Ok(read) => return Ok(read),
+                Err(nb::Error::Other(err)) => return Err(err),
+                Err(nb::Error::WouldBlock) => self.delay.delay_ms(Self::POLL_INTERVAL_MS),
+            }
+        }
 
-        Ok(written as usize)
+        Err(Error::SocketTimeout)
     }
 
-    pub fn socket_read(
+    // Reads until buf is full, for protocols with fixed-size frames. Fails
+    // with Error::SocketClosed if the connection ends first, or
+    // Error::SocketTimeout if buf still isn’t full after timeout_ms, in which
+    // case whatever had been read is lost.
+    pub fn read_exact(
+        &mut self,
+        socket: &Socket,
+        buf: &mut [u8],
+        timeout_ms: u16,
+    ) -> Result<(), Error<SpiError>> {
+        let mut filled = 0;
+
+        for _ in 0..Self::poll_count(timeout_ms) {
+            if filled == buf.len() {
+                return Ok(());
+            }
+
+            match self.socket_read(socket, &mut buf[filled..]) {
+                Ok(0) => return Err(Error::SocketClosed),
+                Ok(read) => filled += read,
+                Err(nb::Error::Other(err)) => return Err(err),
+                Err(nb::Error::WouldBlock) => self.delay.delay_ms(Self::POLL_INTERVAL_MS),
+            }
+        }
+
+        if filled == buf.len() {
+            Ok(())
+        } else {
+            Err(Error::SocketTimeout)
+        }
+    }
+
+    // Reads up to and including the next `delim`, or until buf is full,
+    // e.g. a line of HTTP headers at a time. Whatever the chip hands over
+    // past the delimiter is held on to and returned by the next read of this
+    // socket, so nothing is lost to over-reading.
+    //
+    // Returns what it has so far if the chip runs out of data before the
+    // delimiter (check whether the last byte is `delim` to tell), or
+    // WouldBlock if it had nothing at all. Only one socket at a time can
+    // have bytes held over; if another socket has, this reads a byte at a
+    // time instead, which is correct but slower.
+    pub fn read_until(
         &mut self,
         socket: &Socket,
+        delim: u8,
         buf: &mut [u8],
     ) -> Result<usize, nb::Error<Error<SpiError>>> {
+        let mut len = 0;
+
+        while len < buf.len() {
+            if self.read_ahead.held_by_other(socket) {
+                match self.socket_read_byte(socket) {
+                    Ok(b) => {
+                        buf[len] = b;
+                        len += 1;
+
+                        if b == delim {
+                            break;
+                        }
+
+                        continue;
+                    }
+                    Err(nb::Error::Other(Error::SocketClosed)) => break,
+                    Err(nb::Error::WouldBlock) if len > 0 => break,
+                    Err(err) => return Err(err),
+                }
+            }
+
+            if self.read_ahead.is_empty() {
+                let mut chunk = [0u8; READ_AHEAD_LEN];
+
+                match self.socket_read(socket, &mut chunk) {
+                    Ok(0) => break,
+                    Ok(read) => {
+                        self.read_ahead.buf[..read].copy_from_slice(&chunk[..read]);
+                        self.read_ahead.socket = socket.num();
+                        self.read_ahead.start = 0;
+                        self.read_ahead.end = read;
+                    }
+                    Err(nb::Error::WouldBlock) if len > 0 => break,
+                    Err(err) => return Err(err),
+                }
+            }
+
+            let ahead = &mut self.read_ahead;
+            let pending = &ahead.buf[ahead.start..ahead.end];
+            let room = buf.len() - len;
+
+            let (take, found) = match pending.iter().position(|&b| b == delim) {
+                Some(idx) if idx < room => (idx + 1, true),
+                _ => (core::cmp::min(pending.len(), room), false),
+            };
+
+            buf[len..len + take].copy_from_slice(&pending[..take]);
+            ahead.start += take;
+            len += take;
+
+            if found {
+                break;
+            }
+        }
+
+        Ok(len)
+    }
+
+    // Moves whatever the chip has buffered for the socket into a ring buffer,
+    // as much as will fit, and returns how many bytes that was. Handy for
+    // streams that are consumed at their own pace elsewhere. Returns 0 both
+    // when nothing has arrived and when the ring buffer is full.
+    #[cfg(feature = "spsc")]
+    pub fn socket_pump<const N: usize>(
+        &mut self,
+        socket: &Socket,
+        producer: &mut heapless::spsc::Producer<'_, u8, N>,
+    ) -> Result<usize, Error<SpiError>> {
+        let mut buf = [0u8; 64];
+        let mut total: usize = 0;
+
+        loop {
+            let free = producer.capacity() - producer.len();
+
+            if free == 0 {
+                return Ok(total);
+            }
+
+            let want = core::cmp::min(free, buf.len());
+
+            let read = match self.socket_read(socket, &mut buf[..want]) {
+                Ok(0) | Err(nb::Error::WouldBlock) => return Ok(total),
+                Ok(read) => read,
+                Err(nb::Error::Other(err)) => return Err(err),
+            };
+
+            for &b in &buf[..read] {
+                // Can’t fail: we never read more than there was room for.
+                let _ = producer.enqueue(b);
+            }
+
+            total += read;
+        }
+    }
+
+    // Reads a single byte with GetDataTcp, for parsers that consume input a
+    // byte at a time. Unlike socket_read, a closed socket is reported as
+    // Error::SocketClosed since there’s no byte count to signal EOF with.
+    pub fn socket_read_byte(&mut self, socket: &Socket) -> Result<u8, nb::Error<Error<SpiError>>> {
+        self.check_socket_generation(socket)?;
+
+        let mut byte = [0u8];
+
+        if self.read_ahead.take(socket, &mut byte) > 0 {
+            return Ok(byte[0]);
+        }
+
         let mut available: u16 = 0;
 
         self.send_and_receive(
@@ -196,32 +973,83 @@ where
             Params::of(&mut [SendParam::Byte(socket.num())]),
             Params::of(&mut [RecvParam::LEWord(&mut available)]),
         )
-        .map_err(|err| nb::Error::Other(err))?;
+        .map_err(nb::Error::Other)?;
 
         if available == 0 {
             return match self.socket_status(socket)? {
-                SocketStatus::Closed => Ok(0),
+                SocketStatus::Closed => Err(nb::Error::Other(Error::SocketClosed)),
                 _ => Err(nb::Error::WouldBlock),
             };
         }
 
-        let req_size = core::cmp::min(available, buf.len() as u16);
-
-        let mut read: usize = 0;
+        let mut byte: u8 = 0;
 
         self.send_and_receive(
-            NinaCommand::GetDatabufTcp,
-            Params::with_16_bit_length(&mut [
+            NinaCommand::GetDataTcp,
+            Params::of(&mut [
                 SendParam::Byte(socket.num()),
-                SendParam::LEWord(req_size),
+                // 1 would peek at the byte without consuming it.
+                SendParam::Byte(0),
             ]),
-            Params::with_16_bit_length(&mut [RecvParam::Buffer(buf, &mut read)]),
+            Params::of(&mut [RecvParam::Byte(&mut byte)]),
         )
-        .map_err(|err| {
-            return nb::Error::Other(err);
-        })?;
+        .map_err(nb::Error::Other)?;
 
-        Ok(read)
+        Ok(byte)
+    }
+}
+
+// Bytes that read_until fetched from the chip past the delimiter it was
+// looking for, kept for the next read of the same socket. Only one socket’s
+// worth is kept at a time.
+pub(crate) struct ReadAhead {
+    socket: u8,
+    buf: [u8; READ_AHEAD_LEN],
+    start: usize,
+    end: usize,
+}
+
+const READ_AHEAD_LEN: usize = 64;
+
+impl ReadAhead {
+    pub(crate) fn new() -> Self {
+        ReadAhead {
+            socket: InvalidSocket::INVALID,
+            buf: [0; READ_AHEAD_LEN],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    // Whether there are buffered bytes for any socket other than this one.
+    fn held_by_other(&self, socket: &Socket) -> bool {
+        !self.is_empty() && self.socket != socket.num()
+    }
+
+    fn clear(&mut self, socket: &Socket) -> usize {
+        if self.held_by_other(socket) {
+            return 0;
+        }
+
+        let dropped = self.end - self.start;
+        self.start = 0;
+        self.end = 0;
+        dropped
+    }
+
+    fn take(&mut self, socket: &Socket, buf: &mut [u8]) -> usize {
+        if self.held_by_other(socket) {
+            return 0;
+        }
+
+        let len = core::cmp::min(buf.len(), self.end - self.start);
+        buf[..len].copy_from_slice(&self.buf[self.start..self.start + len]);
+        self.start += len;
+        len
     }
 }
 
@@ -250,13 +1078,15 @@ impl InvalidSocket {
     }
 }
 
+// The error is the number the chip sent: normally 255, meaning it has no
+// socket to give, but anything else not accepted as a socket ends up here too.
 impl TryInto<Socket> for InvalidSocket {
-    type Error = ();
+    type Error = u8;
     fn try_into(self) -> Result<Socket, Self::Error> {
         if Self::valid(self.num) {
             Ok(Socket::new(self.num))
         } else {
-            Err(())
+            Err(self.num)
         }
     }
 }
@@ -269,16 +1099,34 @@ impl From<u8> for InvalidSocket {
 
 pub struct Socket {
     num: u8,
+    // Set by socket_new and accept so that reads and writes can spot a
+    // number that has since been reused. See WifiNina::check_socket_generation.
+    generation: Option<u8>,
 }
 
 impl Socket {
     pub fn new(num: u8) -> Self {
-        Socket { num }
+        Socket {
+            num,
+            generation: None,
+        }
     }
 
     pub fn num(&self) -> u8 {
         self.num
     }
+
+    // Makes a second handle to the same socket number. Socket is deliberately
+    // not Clone, because closing one copy leaves the other pointing at a
+    // number the firmware may hand out again. If you use this, it’s on you to
+    // make sure the socket is only closed once and that no copy is used after
+    // it has been.
+    pub fn duplicate_unchecked(&self) -> Socket {
+        Socket {
+            num: self.num,
+            generation: self.generation,
+        }
+    }
 }
 
 impl core::fmt::Debug for Socket {
@@ -297,15 +1145,20 @@ pub enum Protocol {
     UDP = 1,
     TLS = 2,
 }
-impl Into<u8> for Protocol {
-    fn into(self) -> u8 {
-        self as u8
+impl From<Protocol> for u8 {
+    fn from(protocol: Protocol) -> u8 {
+        protocol as u8
     }
 }
 
 pub enum Destination<'a> {
     Ip([u8; 4]),
     Hostname(&'a str),
+    // A hostname we’ve already looked up. The chip is given both, so that
+    // TLS can check the certificate against the name while the connection
+    // goes to the address. Firmware that ignores the address and resolves
+    // the name itself treats this like Hostname.
+    ResolvedHostname { name: &'a str, ip: [u8; 4] },
 }
 
 impl<'a> Display for Destination<'a> {
@@ -316,11 +1169,12 @@ impl<'a> Display for Destination<'a> {
 
                 for part in arr {
                     f.write_str(part.numtoa_str(10, &mut buf))?;
-                    f.write_char('.'); // yeah, I know
+                    f.write_char('.')?; // yeah, I know
                 }
                 Ok(())
             }
             Destination::Hostname(h) => f.write_str(h),
+            Destination::ResolvedHostname { name, .. } => f.write_str(name),
         }
     }
 }
@@ -363,64 +1217,174 @@ impl From<u8> for SocketStatus {
     }
 }
 
-pub struct ConnectedSocket<'a, CS, B, S, SE, D>
+// A listening server socket, from WifiNina::listen. Like ConnectedSocket it
+// borrows the WifiNina for as long as it’s alive, and it closes the server
+// socket when dropped. Accepted connections borrow the Listener in turn, so
+// handle (or drop) each one before accepting the next.
+pub struct Listener<'a, CS, B, S, SE, D, T = PollCounter>
+where
+    CS: OutputPin,
+    B: InputPin,
+    S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
+    SE: Debug,
+    D: embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    T: crate::util::timer::Timer,
+{
+    wifi: &'a mut WifiNina<CS, B, S, D, T>,
+    socket: Socket,
+    port: u16,
+}
+
+impl<'a, CS, B, S, SE, D, T> Listener<'a, CS, B, S, SE, D, T>
+where
+    CS: OutputPin,
+    B: InputPin,
+    S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
+    SE: Debug,
+    D: embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    T: crate::util::timer::Timer,
+{
+    // Waits up to timeout_ms for a client to connect. Ok(None) if none did.
+    pub fn accept(
+        &mut self,
+        timeout_ms: u16,
+    ) -> Result<Option<ConnectedSocket<'_, CS, B, S, SE, D, T>>, Error<SE>> {
+        match self.wifi.accept(&self.socket, Some(timeout_ms)) {
+            Ok(client) => Ok(Some(client)),
+            Err(Error::SocketTimeout) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn local_port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn socket(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl<'a, CS, B, S, SE, D, T> Drop for Listener<'a, CS, B, S, SE, D, T>
+where
+    CS: OutputPin,
+    B: InputPin,
+    S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
+    SE: Debug,
+    D: embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    T: crate::util::timer::Timer,
+{
+    fn drop(&mut self) {
+        self.wifi.socket_close(&self.socket).ok();
+    }
+}
+
+// An open socket that borrows the WifiNina mutably for as long as it’s alive,
+// so that its reads and writes can’t be interleaved with any other commands.
+// Closes the socket when dropped, unless it came from a pool that wants to
+// keep it open.
+pub struct ConnectedSocket<'a, CS, B, S, SE, D, T = PollCounter>
 where
     CS: OutputPin,
     B: InputPin,
     S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
     SE: Debug,
-    D: embedded_hal::blocking::delay::DelayMs<u16>,
+    D: embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    T: crate::util::timer::Timer,
 {
-    wifi: &'a mut WifiNina<CS, B, S, D>,
+    wifi: &'a mut WifiNina<CS, B, S, D, T>,
     socket: Socket,
+    close_on_drop: bool,
 }
 
-impl<'a, CS, B, S, SE, D> ConnectedSocket<'a, CS, B, S, SE, D>
+impl<'a, CS, B, S, SE, D, T> ConnectedSocket<'a, CS, B, S, SE, D, T>
 where
     CS: OutputPin,
     B: InputPin,
     S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
     SE: Debug,
-    D: embedded_hal::blocking::delay::DelayMs<u16>,
+    D: embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    T: crate::util::timer::Timer,
 {
-    pub fn new(wifi: &'a mut WifiNina<CS, B, S, D>, socket: Socket) -> Self {
-        ConnectedSocket { wifi, socket }
+    pub fn new(wifi: &'a mut WifiNina<CS, B, S, D, T>, socket: Socket) -> Self {
+        ConnectedSocket {
+            wifi,
+            socket,
+            close_on_drop: true,
+        }
+    }
+
+    // For sockets whose lifetime something else manages, like
+    // pool::ConnectionPool.
+    pub(crate) fn kept_open(wifi: &'a mut WifiNina<CS, B, S, D, T>, socket: Socket) -> Self {
+        ConnectedSocket {
+            wifi,
+            socket,
+            close_on_drop: false,
+        }
     }
 
     pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, nb::Error<Error<SE>>> {
         self.wifi.socket_read(&self.socket, buf)
     }
 
+    // Waits up to timeout_ms for something to read; see
+    // WifiNina::socket_read_timeout.
+    pub fn read_timeout(&mut self, buf: &mut [u8], timeout_ms: u16) -> Result<usize, Error<SE>> {
+        self.wifi
+            .socket_read_timeout(&self.socket, buf, Some(timeout_ms))
+    }
+
+    pub fn read_byte(&mut self) -> Result<u8, nb::Error<Error<SE>>> {
+        self.wifi.socket_read_byte(&self.socket)
+    }
+
     pub fn write(&mut self, buf: &[u8]) -> Result<usize, Error<SE>> {
         self.wifi
             .socket_write(&self.socket, &mut buf.iter().cloned())
     }
 
+    pub fn write_all(&mut self, buf: &[u8]) -> Result<(), Error<SE>> {
+        self.wifi.socket_write_all(&self.socket, buf)
+    }
+
     pub fn socket(&self) -> &Socket {
         &self.socket
     }
+
+    // Closes the connection (same as dropping it) but hands back the Socket
+    // so that its number can be passed to WifiNina::reconnect_socket.
+    pub fn into_socket(self) -> Socket {
+        let socket = self.socket.duplicate_unchecked();
+        drop(self);
+        socket
+    }
 }
 
-impl<'a, CS, B, S, SE, D> Drop for ConnectedSocket<'a, CS, B, S, SE, D>
+impl<'a, CS, B, S, SE, D, T> Drop for ConnectedSocket<'a, CS, B, S, SE, D, T>
 where
     CS: OutputPin,
     B: InputPin,
     S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
     SE: Debug,
-    D: embedded_hal::blocking::delay::DelayMs<u16>,
+    D: embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    T: crate::util::timer::Timer,
 {
     fn drop(&mut self) {
-        self.wifi.socket_close(&self.socket).ok();
+        if self.close_on_drop {
+            self.wifi.socket_close(&self.socket).ok();
+        }
     }
 }
 
-impl<'a, CS, B, S, SE, D> core::fmt::Write for ConnectedSocket<'a, CS, B, S, SE, D>
+impl<'a, CS, B, S, SE, D, T> core::fmt::Write for ConnectedSocket<'a, CS, B, S, SE, D, T>
 where
     CS: OutputPin,
     B: InputPin,
     S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
     SE: Debug,
-    D: embedded_hal::blocking::delay::DelayMs<u16>,
+    D: embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    T: crate::util::timer::Timer,
 {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
         match self.write(s.as_bytes()) {
@@ -431,13 +1395,14 @@ where
 }
 
 #[cfg(feature = "genio-traits")]
-impl<'a, CS, B, S, SE, D> genio::Read for ConnectedSocket<'a, CS, B, S, SE, D>
+impl<'a, CS, B, S, SE, D, T> genio::Read for ConnectedSocket<'a, CS, B, S, SE, D, T>
 where
     CS: OutputPin,
     B: InputPin,
     S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
     SE: Debug,
-    D: embedded_hal::blocking::delay::DelayMs<u16>,
+    D: embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    T: crate::util::timer::Timer,
 {
     type ReadError = nb::Error<Error<SE>>;
 
@@ -447,19 +1412,38 @@ where
 }
 
 #[cfg(feature = "genio-traits")]
-impl<'a, CS, B, S, SE, D> genio::Write for ConnectedSocket<'a, CS, B, S, SE, D>
+impl<'a, CS, B, S, SE, D, T> genio::Write for ConnectedSocket<'a, CS, B, S, SE, D, T>
 where
     CS: OutputPin,
     B: InputPin,
     S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
     SE: Debug,
-    D: embedded_hal::blocking::delay::DelayMs<u16>,
+    D: embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    T: crate::util::timer::Timer,
 {
     type WriteError = Error<SE>;
     type FlushError = void::Void;
 
+    // The chip accepts nothing when its send buffer is full, but genio
+    // callers take Ok(0) to mean the stream has ended. So give the buffer
+    // 100ms to drain, and report a timeout if it doesn’t.
     fn write(&mut self, buf: &[u8]) -> Result<usize, Self::WriteError> {
-        self.write(buf)
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        for _ in 0..10 {
+            let written = ConnectedSocket::write(self, buf)?;
+
+            if written > 0 {
+                return Ok(written);
+            }
+
+            self.wifi.count(Event::Retry);
+            self.wifi.delay.delay_ms(10);
+        }
+
+        Err(Error::SocketTimeout)
     }
 
     fn flush(&mut self) -> Result<(), Self::FlushError> {
@@ -472,3 +1456,358 @@ where
         false
     }
 }
+
+// For host-side tools driving a NINA over a USB-SPI bridge. Reads that would
+// block come out as io::ErrorKind::WouldBlock, like a non-blocking std socket,
+// and a closed socket reads as EOF. Writes while the chip’s buffer is full
+// are WouldBlock too, since Ok(0) would make write_all give up with
+// WriteZero; write_all itself waits like WifiNina::socket_write_all.
+#[cfg(feature = "std")]
+fn io_error<SE: Debug>(err: Error<SE>) -> std::io::Error {
+    let kind = match err {
+        Error::SocketClosed => std::io::ErrorKind::ConnectionAborted,
+        Error::SocketTimeout | Error::ResponseTimeout => std::io::ErrorKind::TimedOut,
+        _ => std::io::ErrorKind::Other,
+    };
+
+    std::io::Error::new(kind, std::format!("{:?}", err))
+}
+
+#[cfg(feature = "std")]
+impl<'a, CS, B, S, SE, D, T> std::io::Read for ConnectedSocket<'a, CS, B, S, SE, D, T>
+where
+    CS: OutputPin,
+    B: InputPin,
+    S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
+    SE: Debug,
+    D: embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    T: crate::util::timer::Timer,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match ConnectedSocket::read(self, buf) {
+            Ok(read) => Ok(read),
+            Err(nb::Error::WouldBlock) => Err(std::io::ErrorKind::WouldBlock.into()),
+            Err(nb::Error::Other(err)) => Err(io_error(err)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, CS, B, S, SE, D, T> std::io::Write for ConnectedSocket<'a, CS, B, S, SE, D, T>
+where
+    CS: OutputPin,
+    B: InputPin,
+    S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
+    SE: Debug,
+    D: embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    T: crate::util::timer::Timer,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match ConnectedSocket::write(self, buf) {
+            Ok(0) if !buf.is_empty() => Err(std::io::ErrorKind::WouldBlock.into()),
+            Ok(written) => Ok(written),
+            Err(err) => Err(io_error(err)),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        ConnectedSocket::write_all(self, buf).map_err(io_error)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+
+    #[test]
+    fn read_until_keeps_bytes_past_the_delimiter() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let socket = Socket::new(0);
+
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::AvailableDataTcp, &[&[8, 0]]);
+
+            // GetDatabufTcp answers with a 16-bit length.
+            bus.respond(&[
+                0xE0, 0xC5, 1, 0, 8, b'a', b'b', b'c', b'\n', b'd', b'e', b'f', b'\n', 0xEE,
+            ]);
+        }
+
+        let mut line = [0u8; 16];
+
+        let len = wifi.read_until(&socket, b'\n', &mut line).unwrap();
+        assert_eq!(&line[..len], b"abc\n");
+
+        let sent = bus.borrow().sent.len();
+
+        let len = wifi.read_until(&socket, b'\n', &mut line).unwrap();
+        assert_eq!(&line[..len], b"def\n");
+
+        // The second line came from what was held over, not the chip.
+        assert_eq!(bus.borrow().sent.len(), sent);
+    }
+
+    #[test]
+    fn a_reused_socket_number_is_rejected() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetSocket, &[&[3]]);
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetSocket, &[&[3]]);
+
+        let stale = wifi.socket_new().unwrap();
+        let current = wifi.socket_new().unwrap();
+
+        let sent = bus.borrow().sent.len();
+        let result = wifi.socket_write(&stale, &mut b"hi".iter().cloned());
+        assert!(matches!(result, Err(Error::SocketReused)));
+        // Caught before anything was sent.
+        assert_eq!(bus.borrow().sent.len(), sent);
+
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SendDataTcp, &[&[2, 0]]);
+        assert_eq!(
+            wifi.socket_write(&current, &mut b"hi".iter().cloned())
+                .unwrap(),
+            2
+        );
+
+        // Nothing survives a reset.
+        wifi.reset(&mut mock::MockPin).unwrap();
+        let result = wifi.socket_write(&current, &mut b"hi".iter().cloned());
+        assert!(matches!(result, Err(Error::SocketReused)));
+    }
+
+    #[test]
+    fn reconnect_socket_reopens_the_same_number() {
+        let (mut wifi, bus) = mock::wifi_nina();
+
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::GetSocket, &[&[3]]);
+            bus.respond_with(NinaCommand::StartClientTcp, &[&[1]]);
+            bus.respond_with(NinaCommand::GetClientStateTcp, &[&[4]]);
+            bus.respond_with(NinaCommand::StopClientTcp, &[&[1]]);
+            bus.respond_with(NinaCommand::StartClientTcp, &[&[1]]);
+            bus.respond_with(NinaCommand::GetClientStateTcp, &[&[4]]);
+            bus.respond_with(NinaCommand::SendDataTcp, &[&[2, 0]]);
+        }
+
+        let socket = wifi
+            .connect(Protocol::TCP, Destination::Ip([10, 0, 0, 1]), 80)
+            .unwrap()
+            .into_socket();
+        assert_eq!(wifi.open_sockets, 0);
+
+        bus.borrow_mut().sent.clear();
+        let mut conn = wifi
+            .reconnect_socket(socket, Protocol::TCP, Destination::Ip([10, 0, 0, 1]), 80)
+            .unwrap();
+
+        // Straight to StartClientTcp on socket 3, with no GetSocket.
+        assert_eq!(&bus.borrow().sent[..2], &[0xE0, 0x2D]);
+        assert_eq!(bus.borrow().sent[12], 3);
+        // Open again, so flush_all and friends see it.
+        assert_eq!(conn.wifi.open_sockets, 1 << 3);
+        assert_eq!(conn.write(b"hi").unwrap(), 2);
+
+        // Dropping the reconnected socket closes it again.
+        bus.borrow_mut()
+            .respond_with(NinaCommand::StopClientTcp, &[&[1]]);
+    }
+
+    #[test]
+    fn reconnect_socket_rejects_a_number_handed_out_since() {
+        let (mut wifi, bus) = mock::wifi_nina();
+
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::GetSocket, &[&[3]]);
+            bus.respond_with(NinaCommand::StartClientTcp, &[&[1]]);
+            bus.respond_with(NinaCommand::GetClientStateTcp, &[&[4]]);
+            bus.respond_with(NinaCommand::StopClientTcp, &[&[1]]);
+            // Someone else gets 3 while we aren’t using it.
+            bus.respond_with(NinaCommand::GetSocket, &[&[3]]);
+        }
+
+        let socket = wifi
+            .connect(Protocol::TCP, Destination::Ip([10, 0, 0, 1]), 80)
+            .unwrap()
+            .into_socket();
+        let other = wifi.socket_new().unwrap();
+
+        let sent = bus.borrow().sent.len();
+        let result =
+            wifi.reconnect_socket(socket, Protocol::TCP, Destination::Ip([10, 0, 0, 1]), 80);
+        assert!(matches!(result, Err(Error::SocketReused)));
+        drop(result);
+
+        // Nothing was sent, so the other connection is left alone.
+        assert_eq!(bus.borrow().sent.len(), sent);
+        assert_eq!(wifi.open_sockets, 1 << 3);
+        assert!(wifi.check_socket_generation(&other).is_ok());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn io_write_waits_out_a_full_buffer() {
+        let (mut wifi, bus) = mock::wifi_nina();
+
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::SendDataTcp, &[&[0, 0]]);
+            bus.respond_with(NinaCommand::SendDataTcp, &[&[0, 0]]);
+            bus.respond_with(NinaCommand::SendDataTcp, &[&[2, 0]]);
+        }
+
+        let mut conn = ConnectedSocket::kept_open(&mut wifi, Socket::new(2));
+
+        let err = std::io::Write::write(&mut conn, b"hi").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+        // Not WriteZero: write_all waits for the chip to take it.
+        std::io::Write::write_all(&mut conn, b"hi").unwrap();
+    }
+
+    #[test]
+    fn socket_write_splits_at_max_send_chunk() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let socket = Socket::new(2);
+        let data = [0x55u8; MAX_SEND_CHUNK + 10];
+
+        bus.borrow_mut().respond_with(
+            NinaCommand::SendDataTcp,
+            &[&(MAX_SEND_CHUNK as u16).to_le_bytes()],
+        );
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SendDataTcp, &[&[10, 0]]);
+
+        let written = wifi
+            .socket_write(&socket, &mut data.iter().cloned())
+            .unwrap();
+        assert_eq!(written, data.len());
+
+        let sent = &bus.borrow().sent;
+        // Header, then the socket and the chunk, with 16-bit lengths.
+        assert_eq!(&sent[..8], &[0xE0, 0x44, 2, 0, 1, 2, 0x0F, 0xA0]);
+        // 3 + 3 + 2 + 4000 + End, padded to 4012.
+        assert_eq!(sent[4008], 0xEE);
+        assert_eq!(&sent[4012..4020], &[0xE0, 0x44, 2, 0, 1, 2, 0, 10]);
+    }
+
+    #[test]
+    fn socket_write_stops_when_the_chip_takes_less_than_a_chunk() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let socket = Socket::new(2);
+        let data = [0x55u8; MAX_SEND_CHUNK + 10];
+
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SendDataTcp, &[&[100, 0]]);
+
+        let written = wifi
+            .socket_write(&socket, &mut data.iter().cloned())
+            .unwrap();
+
+        assert_eq!(written, 100);
+        assert_eq!(bus.borrow().sent.len(), 4012);
+    }
+
+    #[test]
+    fn reads_socket_data_in_bursts() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let socket = Socket::new(0);
+        wifi.config_mut().inter_byte_delay_us = 5;
+
+        let mut read_with_bursts_of = |burst_len: u8| {
+            wifi.config_mut().read_burst_len = burst_len;
+
+            {
+                let mut bus = bus.borrow_mut();
+                bus.delays_us = 0;
+                bus.respond_with(NinaCommand::AvailableDataTcp, &[&[10, 0]]);
+                bus.respond(&[
+                    0xE0, 0xC5, 1, 0, 10, b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8',
+                    b'9', 0xEE,
+                ]);
+            }
+
+            let mut buf = [0u8; 16];
+            let len = wifi.socket_read(&socket, &mut buf).unwrap();
+            assert_eq!(&buf[..len], b"0123456789", "burst {}", burst_len);
+
+            bus.borrow().delays_us
+        };
+
+        let paced = read_with_bursts_of(1);
+        let burst = read_with_bursts_of(4);
+
+        // Ten bytes paced one at a time, then in bursts of 4, 4 and a short
+        // tail of 2: seven pauses fewer.
+        assert_eq!(paced - burst, 7);
+    }
+
+    #[test]
+    fn udp_send_points_the_socket_then_stages_and_sends() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let socket = Socket::new(1);
+        // Not that UDP needs a connection, but it shouldn’t be asked for.
+        wifi.config_mut().require_connected = true;
+
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::StartClientTcp, &[&[1]]);
+            bus.respond_with(NinaCommand::InsertDataBuf, &[&[1]]);
+            bus.respond_with(NinaCommand::SendUdpData, &[&[1]]);
+        }
+
+        wifi.udp_send(&socket, [10, 0, 0, 9], 5353, b"hey").unwrap();
+
+        let expected: &[u8] = &[
+            // 10.0.0.9:5353, socket 1, UDP.
+            0xE0, 0x2D, 4, 4, 10, 0, 0, 9, 2, 0x14, 0xE9, 1, 1, 1, 1, 0xEE, //
+            // The payload, with 16-bit lengths.
+            0xE0, 0x46, 2, 0, 1, 1, 0, 3, b'h', b'e', b'y', 0xEE, //
+            0xE0, 0x39, 1, 1, 1, 0xEE, 0, 0,
+        ];
+        assert_eq!(bus.borrow().sent, expected);
+
+        // Nothing for flush_all to wait on.
+        assert_eq!(wifi.open_sockets, 0);
+    }
+
+    #[test]
+    fn udp_recv_reports_the_sender() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let socket = Socket::new(1);
+
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::AvailableDataTcp, &[&[0, 0]]);
+
+            bus.respond_with(NinaCommand::AvailableDataTcp, &[&[4, 0]]);
+            bus.respond(&[0xE0, 0xC5, 1, 0, 4, b'p', b'i', b'n', b'g', 0xEE]);
+            bus.respond_with(
+                NinaCommand::GetRemoteData,
+                &[&[192, 168, 1, 20], &[0x1F, 0x90]],
+            );
+        }
+
+        let mut buf = [0u8; 16];
+
+        assert!(matches!(
+            wifi.udp_recv(&socket, &mut buf),
+            Err(nb::Error::WouldBlock)
+        ));
+
+        let (len, ip, port) = wifi.udp_recv(&socket, &mut buf).unwrap();
+        assert_eq!(&buf[..len], b"ping");
+        assert_eq!(ip, [192, 168, 1, 20]);
+        assert_eq!(port, 8080);
+    }
+}
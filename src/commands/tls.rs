@@ -0,0 +1,143 @@
+use embedded_hal::{
+    digital::v2::{InputPin, OutputPin},
+    spi::FullDuplex,
+};
+
+use crate::{
+    commands::{
+        socket::{Destination, Protocol},
+        *,
+    },
+    Error, WifiNina,
+};
+
+// Sizes of the firmware’s buffers for the client certificate and its private
+// key.
+pub const MAX_CLIENT_CERT_LEN: usize = 1300;
+pub const MAX_CERT_KEY_LEN: usize = 1700;
+
+impl<CsPin, BusyPin, Spi, SpiError, Delay, Timer> WifiNina<CsPin, BusyPin, Spi, Delay, Timer>
+where
+    BusyPin: InputPin,
+    CsPin: OutputPin,
+    Spi:
+        FullDuplex<u8, Error = SpiError> + embedded_hal::blocking::spi::Write<u8, Error = SpiError>,
+    SpiError: Debug,
+    //+ embedded_hal::blocking::spi::WriteIter<u8, Error = SpiError>,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    Timer: crate::util::timer::Timer,
+{
+    // Adds a root certificate to check TLS servers against, for services
+    // signed by a private CA. The firmware has no command for adding to the
+    // CA bundle baked into it; adding a CA means rebuilding the firmware.
+    pub fn set_root_ca(&mut self, _pem_or_der: &[u8]) -> Result<(), Error<SpiError>> {
+        Err(Error::Unsupported)
+    }
+
+    // Sets the certificate and private key presented to servers that ask for
+    // one (mutual TLS). Both are PEM. Call this before socket_open with
+    // Protocol::TLS; the firmware keeps using them for every TLS connection
+    // until it’s reset.
+    pub fn set_client_cert(&mut self, cert: &[u8], key: &[u8]) -> Result<(), Error<SpiError>> {
+        if cert.len() > MAX_CLIENT_CERT_LEN || key.len() > MAX_CERT_KEY_LEN {
+            return Err(Error::CertificateTooLong);
+        }
+
+        // The firmware ignores the length byte and copies a whole buffer’s
+        // worth from where the data starts, so these have to go with 8-bit
+        // lengths to land in the right place, even though one byte can’t
+        // hold their real length.
+        self.send_and_receive(
+            NinaCommand::SetClientCert,
+            Params::of(&mut [SendParam::Bytes(&mut cert.iter().cloned())]),
+            Params::of(&mut [RecvParam::Ack]),
+        )?;
+
+        self.send_and_receive(
+            NinaCommand::SetCertKey,
+            Params::of(&mut [SendParam::Bytes(&mut key.iter().cloned())]),
+            Params::of(&mut [RecvParam::Ack]),
+        )
+    }
+
+    // One whole request over TLS: connects to host (which the chip resolves,
+    // and checks the certificate against), sends request, reads the response
+    // into response until the server closes the connection or response is
+    // full, then closes. Returns the length of the response.
+    //
+    // The server has to close the connection at the end of its response,
+    // e.g. because the request asked for "Connection: close"; otherwise this
+    // fails with Error::SocketTimeout after the config’s read_timeout_ms.
+    pub fn tls_request(
+        &mut self,
+        host: &str,
+        port: u16,
+        request: &[u8],
+        response: &mut [u8],
+    ) -> Result<usize, Error<SpiError>> {
+        let timeout_ms = self.config.read_timeout_ms;
+
+        let mut connection = self.connect(Protocol::TLS, Destination::Hostname(host), port)?;
+
+        connection.write_all(request)?;
+
+        let mut len = 0;
+
+        while len < response.len() {
+            match connection.read_timeout(&mut response[len..], timeout_ms)? {
+                0 => break,
+                read => len += read,
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+
+    #[test]
+    fn client_cert_data_starts_where_the_firmware_copies_from() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SetClientCert, &[&[1]]);
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SetCertKey, &[&[1]]);
+
+        let cert = [0xC3u8; 300];
+        let key = [0x4Bu8; 20];
+
+        wifi.set_client_cert(&cert, &key).unwrap();
+
+        let sent = &bus.borrow().sent;
+
+        // nina-fw copies from command[4] whatever the length byte says, so
+        // the 8-bit framing (and its truncated length) is what puts the
+        // cert there.
+        assert_eq!(&sent[..4], &[0xE0, 0x40, 1, 300u16 as u8]);
+        assert_eq!(&sent[4..304], &cert[..]);
+        assert_eq!(sent[304], 0xEE);
+
+        // Padded to 308, then the key.
+        assert_eq!(&sent[308..312], &[0xE0, 0x41, 1, 20]);
+        assert_eq!(&sent[312..332], &key[..]);
+    }
+
+    #[test]
+    fn oversized_client_certs_are_rejected_before_sending() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let big = [0u8; MAX_CERT_KEY_LEN + 1];
+
+        let result = wifi.set_client_cert(&big[..MAX_CLIENT_CERT_LEN + 1], &[0; 10]);
+        assert!(matches!(result, Err(Error::CertificateTooLong)));
+
+        let result = wifi.set_client_cert(&[0; 10], &big);
+        assert!(matches!(result, Err(Error::CertificateTooLong)));
+
+        assert!(bus.borrow().sent.is_empty());
+    }
+}
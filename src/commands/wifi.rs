@@ -3,7 +3,11 @@ use embedded_hal::{
     spi::FullDuplex,
 };
 
-use crate::{commands::*, Error, WifiNina};
+use crate::{
+    commands::{diagnostics::FirmwareVersion, *},
+    credentials::{CredentialStore, Credentials, MAX_SSID_LEN},
+    Error, WifiNina,
+};
 
 #[repr(u8)]
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -41,7 +45,136 @@ impl From<u8> for WifiStatus {
     }
 }
 
-impl<CsPin, BusyPin, Spi, SpiError, Delay> WifiNina<CsPin, BusyPin, Spi, Delay>
+// What the radio is currently being used for, as far as can be told from
+// WifiStatus. Station covers trying to connect and having lost a connection
+// as well as being connected.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WifiMode {
+    Idle,
+    Station,
+    AccessPoint,
+}
+
+impl From<WifiStatus> for WifiMode {
+    fn from(status: WifiStatus) -> Self {
+        match status {
+            WifiStatus::ApListening | WifiStatus::ApConnected | WifiStatus::ApFailed => {
+                WifiMode::AccessPoint
+            }
+
+            WifiStatus::Connected
+            | WifiStatus::ConnectFailed
+            | WifiStatus::ConnectionLost
+            | WifiStatus::NoSsidAvailable => WifiMode::Station,
+
+            WifiStatus::Idle
+            | WifiStatus::ScanCompleted
+            | WifiStatus::Disconnected
+            | WifiStatus::UnknownStatus => WifiMode::Idle,
+        }
+    }
+}
+
+// Encryption types as reported by the firmware. The values are the 802.11
+// cipher suite numbers that the Arduino WiFiNINA library uses, which the
+// firmware maps the ESP32’s auth modes onto.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EncryptionType {
+    // WPA (TKIP)
+    Wpa,
+    // WPA2 (CCMP)
+    Wpa2,
+    Wep,
+    // An open network
+    None,
+    // WPA/WPA2 mixed mode
+    Auto,
+
+    Unknown(u8),
+}
+
+impl From<u8> for EncryptionType {
+    fn from(b: u8) -> Self {
+        match b {
+            2 => EncryptionType::Wpa,
+            4 => EncryptionType::Wpa2,
+            5 => EncryptionType::Wep,
+            7 => EncryptionType::None,
+            8 => EncryptionType::Auto,
+
+            _ => EncryptionType::Unknown(b),
+        }
+    }
+}
+
+impl EncryptionType {
+    // The first firmware to map the ESP32’s auth modes onto the Arduino
+    // values that From<u8> expects.
+    const ARDUINO_VALUES_SINCE: FirmwareVersion = FirmwareVersion::new(1, 2, 0);
+
+    // Decodes an encryption type byte from a current or scanned network
+    // according to the firmware that sent it. Firmware before 1.2.0 passes on
+    // the ESP-IDF wifi_auth_mode_t as is, where e.g. 2 is WPA rather than
+    // WPA2 and 4 is mixed mode rather than WPA2.
+    pub fn from_firmware(version: FirmwareVersion, b: u8) -> Self {
+        if version >= Self::ARDUINO_VALUES_SINCE {
+            return b.into();
+        }
+
+        match b {
+            0 => EncryptionType::None,
+            1 => EncryptionType::Wep,
+            2 => EncryptionType::Wpa,
+            3 => EncryptionType::Wpa2,
+            4 => EncryptionType::Auto,
+
+            _ => EncryptionType::Unknown(b),
+        }
+    }
+}
+
+// How hard the ESP32 tries to save power on the radio between beacons.
+#[repr(u8)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PowerMode {
+    // Always listening. The firmware’s default.
+    Active = 0,
+    // Sleeps the radio between beacons, at the cost of latency.
+    LowPower = 1,
+}
+
+// How long wifi_connect_timed took, in milliseconds. These are counted in
+// poll intervals, so they’re approximate: they leave out the time spent
+// talking to the chip and are rounded up to the next poll.
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectTiming {
+    pub status: WifiStatus,
+    // Until the chip had joined the access point, going by it reporting a
+    // BSSID. None if that was never seen before it had an address.
+    pub association_ms: Option<u32>,
+    // From joining until the chip had an address from DHCP (which is when
+    // it reports Connected).
+    pub dhcp_ms: Option<u32>,
+    pub total_ms: u32,
+}
+
+// How wifi_connect and the other connect methods without a timeout of their
+// own wait for the chip to join.
+const CONNECT_ATTEMPTS: u16 = 5;
+const CONNECT_POLL_INTERVAL_MS: u16 = 1000;
+
+// Everything about the current association that the firmware will tell us.
+#[derive(Debug, Clone)]
+pub struct LinkInfo {
+    pub ssid: heapless::String<MAX_SSID_LEN>,
+    pub rssi: i32,
+    pub encryption_type: EncryptionType,
+    pub bssid: [u8; 6],
+    // None if the access point wasn’t in the results of the last scan.
+    pub channel: Option<u8>,
+}
+
+impl<CsPin, BusyPin, Spi, SpiError, Delay, Timer> WifiNina<CsPin, BusyPin, Spi, Delay, Timer>
 where
     BusyPin: InputPin,
     CsPin: OutputPin,
@@ -49,7 +182,9 @@ where
         FullDuplex<u8, Error = SpiError> + embedded_hal::blocking::spi::Write<u8, Error = SpiError>,
     SpiError: Debug,
     //+ embedded_hal::blocking::spi::WriteIter<u8, Error = SpiError>,
-    Delay: embedded_hal::blocking::delay::DelayMs<u16>,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    Timer: crate::util::timer::Timer,
 {
     pub fn wifi_status(&mut self) -> Result<WifiStatus, Error<SpiError>> {
         let mut status: u8 = 255;
@@ -63,6 +198,77 @@ where
         Ok(status.into())
     }
 
+    // The firmware has no command for this, so it’s worked out from
+    // wifi_status.
+    pub fn wifi_mode(&mut self) -> Result<WifiMode, Error<SpiError>> {
+        Ok(self.wifi_status()?.into())
+    }
+
+    // The firmware’s version string, e.g. "1.7.4".
+    pub fn firmware_version<const N: usize>(
+        &mut self,
+    ) -> Result<heapless::String<N>, Error<SpiError>> {
+        let mut buf = [0u8; 16];
+        let mut len: usize = 0;
+
+        self.send_and_receive(
+            NinaCommand::GetFirmwareVersion,
+            Params::none(),
+            Params::of(&mut [RecvParam::Buffer(&mut buf, &mut len)]),
+        )?;
+
+        // The firmware includes the string’s NUL terminator.
+        let version = core::str::from_utf8(&buf[..len])
+            .map_err(|_| Error::InvalidUtf8)?
+            .trim_end_matches('\0');
+
+        let mut out = heapless::String::new();
+        out.push_str(version)
+            .map_err(|_| Error::MismatchedParamSize(N, len))?;
+
+        Ok(out)
+    }
+
+    // The firmware version as (major, minor, patch), for comparing against
+    // the version a feature needs, e.g. `>= (1, 5, 0)`. Fails with
+    // Error::UnrecognizedFirmwareVersion if it isn’t in that form.
+    pub fn firmware_version_tuple(&mut self) -> Result<(u8, u8, u8), Error<SpiError>> {
+        let version = self.firmware()?;
+
+        Ok((version.major, version.minor, version.patch))
+    }
+
+    // The chip’s own MAC address, in the order it appears on the air.
+    pub fn mac_address(&mut self) -> Result<[u8; 6], Error<SpiError>> {
+        let mut mac = [0u8; 6];
+
+        self.send_and_receive(
+            NinaCommand::GetMacAddress,
+            Params::of(&mut [SendParam::Byte(Self::DUMMY_DATA)]),
+            Params::of(&mut [RecvParam::ByteArray(&mut mac)]),
+        )?;
+
+        // Like the BSSIDs, this comes last byte first.
+        mac.reverse();
+
+        Ok(mac)
+    }
+
+    // Enforces Config::require_connected.
+    pub(crate) fn check_connected(&mut self) -> Result<(), Error<SpiError>> {
+        if self.config.require_connected && self.wifi_status()? != WifiStatus::Connected {
+            return Err(Error::NotConnected);
+        }
+
+        Ok(())
+    }
+
+    // Sets the station’s hostname, which is also what the DHCP client sends
+    // as its hostname option: the firmware has only the one. It’s sent while
+    // getting a lease, so this needs to be called before wifi_connect. Set
+    // after connecting, it doesn’t show up in the router’s lease table until
+    // the next connection, which until then lists the ESP32’s default
+    // "espressif".
     pub fn set_hostname(&mut self, hostname: &str) -> Result<(), Error<SpiError>> {
         self.send_and_receive(
             NinaCommand::SetHostname,
@@ -71,46 +277,624 @@ where
         )
     }
 
+    // Firmware too old to have the command answers with an error, which
+    // comes back as Error::Unsupported.
+    pub fn set_power_mode(&mut self, mode: PowerMode) -> Result<(), Error<SpiError>> {
+        self.send_and_receive(
+            NinaCommand::SetPowerMode,
+            Params::of(&mut [SendParam::Byte(mode as u8)]),
+            Params::of(&mut [RecvParam::Ack]),
+        )
+        .map_err(|err| match err {
+            Error::ErrorResponse => Error::Unsupported,
+            err => err,
+        })
+    }
+
+    // Sets the two-letter regulatory domain (e.g. "JP") that determines the
+    // usable channels and TX power. The firmware has no set-country command;
+    // the ESP32 keeps the country it was built with.
+    pub fn set_country_code(&mut self, _code: &str) -> Result<(), Error<SpiError>> {
+        Err(Error::Unsupported)
+    }
+
+    // Waits up to about 5s for the connection. Use wifi_connect_with_timeout
+    // for access points that take longer than that.
     pub fn wifi_connect(
         &mut self,
         ssid: &str,
         password: Option<&str>,
     ) -> Result<WifiStatus, Error<SpiError>> {
-        match password {
-            None => {
-                self.send_and_receive(
-                    NinaCommand::SetNetwork,
-                    Params::of(&mut [SendParam::Bytes(&mut ssid.bytes())]),
-                    Params::of(&mut [RecvParam::Ack]),
-                )?;
-            }
+        self.wifi_connect_with_timeout(ssid, password, CONNECT_ATTEMPTS, CONNECT_POLL_INTERVAL_MS)
+    }
 
-            Some(password) => {
-                self.send_and_receive(
-                    NinaCommand::SetNetworkAndPassphrase,
-                    Params::of(&mut [
-                        SendParam::Bytes(&mut ssid.bytes()),
-                        SendParam::Bytes(&mut password.bytes()),
-                    ]),
-                    Params::of(&mut [RecvParam::Ack]),
-                )?;
-            }
+    // Connects, checking the status up to attempts times, interval_ms apart.
+    // A ConnectFailed status (e.g. a wrong password) ends the wait early.
+    pub fn wifi_connect_with_timeout(
+        &mut self,
+        ssid: &str,
+        password: Option<&str>,
+        attempts: u16,
+        interval_ms: u16,
+    ) -> Result<WifiStatus, Error<SpiError>> {
+        self.begin_connect(ssid, password)?;
+        self.wait_for_connection(attempts, interval_ms)
+    }
+
+    // For calling periodically to get back onto the network after the
+    // connection drops. Does nothing if we’re still connected: giving the
+    // chip the network again would make it reconnect, closing every open
+    // socket.
+    pub fn ensure_connected(
+        &mut self,
+        ssid: &str,
+        password: Option<&str>,
+    ) -> Result<WifiStatus, Error<SpiError>> {
+        match self.wifi_status()? {
+            WifiStatus::Connected => Ok(WifiStatus::Connected),
+            _ => self.wifi_connect(ssid, password),
+        }
+    }
+
+    // Joins a WPA2-Enterprise network using PEAP/TTLS-style credentials
+    // (an outer identity plus the username and password checked by the
+    // RADIUS server). Certificate-based EAP-TLS isn’t covered here.
+    pub fn wifi_connect_enterprise(
+        &mut self,
+        ssid: &str,
+        identity: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<WifiStatus, Error<SpiError>> {
+        let credentials = [
+            (NinaCommand::SetEnterpriseIdent, identity),
+            (NinaCommand::SetEnterpriseUsername, username),
+            (NinaCommand::SetEnterprisePassword, password),
+        ];
+
+        for (command, value) in credentials {
+            self.send_and_receive(
+                command,
+                Params::of(&mut [SendParam::Bytes(&mut value.bytes())]),
+                Params::of(&mut [RecvParam::Ack]),
+            )?;
+        }
+
+        // Only switches the station over to enterprise auth; it takes no
+        // params. Joining is then the same SetNetwork as an open network,
+        // which is how the Arduino and Adafruit libraries do it too.
+        self.send_and_receive(
+            NinaCommand::SetEnterpriseEnable,
+            Params::none(),
+            Params::of(&mut [RecvParam::Ack]),
+        )?;
+
+        self.begin_connect(ssid, None)?;
+        self.wait_for_connection(CONNECT_ATTEMPTS, CONNECT_POLL_INTERVAL_MS)
+    }
+
+    // Joins a network secured with WEP, using the key at key_index (0 to 3)
+    // of the access point’s key table. Only here for old equipment that
+    // can’t do anything better.
+    pub fn wifi_connect_wep(
+        &mut self,
+        ssid: &str,
+        key_index: u8,
+        key: &[u8],
+    ) -> Result<WifiStatus, Error<SpiError>> {
+        if key.len() != 5 && key.len() != 13 {
+            return Err(Error::InvalidKey);
         }
 
+        self.send_and_receive(
+            NinaCommand::SetKey,
+            Params::of(&mut [
+                SendParam::Bytes(&mut ssid.bytes()),
+                SendParam::Byte(key_index),
+                SendParam::Bytes(&mut key.iter().cloned()),
+            ]),
+            Params::of(&mut [RecvParam::Ack]),
+        )?;
+
+        self.wait_for_connection(CONNECT_ATTEMPTS, CONNECT_POLL_INTERVAL_MS)
+    }
+
+    fn wait_for_connection(
+        &mut self,
+        attempts: u16,
+        interval_ms: u16,
+    ) -> Result<WifiStatus, Error<SpiError>> {
         let mut last_status = WifiStatus::UnknownStatus;
 
         // Wait for the Wifi to stabilize.
-        for _ in 0..5 {
+        for _ in 0..attempts {
             last_status = self.wifi_status()?;
 
-            if last_status == WifiStatus::Connected {
-                return Ok(last_status);
+            match self.connect_progress(last_status) {
+                Ok(status) => return Ok(status),
+                Err(nb::Error::Other(err)) => return Err(err),
+                Err(nb::Error::WouldBlock) => {}
+            }
+
+            self.delay.delay_ms(interval_ms);
+        }
+
+        Err(self.connection_failed(last_status))
+    }
+
+    // Like wifi_connect, but polls more often and reports how long joining
+    // the access point and then getting an address each took, for working
+    // out which of them is slowing down a cold boot.
+    pub fn wifi_connect_timed(
+        &mut self,
+        ssid: &str,
+        password: Option<&str>,
+    ) -> Result<ConnectTiming, Error<SpiError>> {
+        const POLL_MS: u16 = 100;
+        const TIMEOUT_MS: u32 = 5000;
+
+        self.begin_connect(ssid, password)?;
+
+        let mut last_status = WifiStatus::UnknownStatus;
+        let mut associated_at = None;
+        let mut elapsed: u32 = 0;
+
+        while elapsed < TIMEOUT_MS {
+            last_status = self.wifi_status()?;
+
+            match self.connect_progress(last_status) {
+                Ok(status) => {
+                    return Ok(ConnectTiming {
+                        status,
+                        association_ms: associated_at,
+                        dhcp_ms: associated_at.map(|at| elapsed - at),
+                        total_ms: elapsed,
+                    })
+                }
+                Err(nb::Error::Other(err)) => return Err(err),
+                Err(nb::Error::WouldBlock) => {}
+            }
+
+            if associated_at.is_none() && self.current_bssid()? != [0; 6] {
+                associated_at = Some(elapsed);
+            }
+
+            self.delay.delay_ms(POLL_MS);
+            elapsed += POLL_MS as u32;
+        }
+
+        Err(self.connection_failed(last_status))
+    }
+
+    // Connects to a network that doesn’t broadcast its SSID. There’s no
+    // separate command or flag for this: the firmware hands the SSID to the
+    // ESP32 as part of the station config, and the ESP32 then sends directed
+    // probe requests for it rather than waiting for beacons, so hidden
+    // networks are found the same way as visible ones. That’s how every
+    // nina-fw we know of behaves; if connecting still fails, make sure the
+    // SSID is spelled exactly, since there’s no scan result to match it
+    // against. This exists so that calling code can say what it means, and so
+    // there’s one place to change if some firmware ever does need more.
+    pub fn wifi_connect_hidden(
+        &mut self,
+        ssid: &str,
+        password: &str,
+    ) -> Result<WifiStatus, Error<SpiError>> {
+        self.wifi_connect(ssid, Some(password))
+    }
+
+    // Connects to one particular access point of a network, going by its
+    // BSSID, so that the chip doesn’t join (or roam to) a weaker one with the
+    // same SSID. The firmware has no connect command that takes a BSSID.
+    pub fn wifi_connect_bssid(
+        &mut self,
+        _ssid: &str,
+        _password: &str,
+        _bssid: [u8; 6],
+    ) -> Result<WifiStatus, Error<SpiError>> {
+        Err(Error::Unsupported)
+    }
+
+    // Makes the chip forget the network it was told to join, e.g. for a
+    // factory reset. The firmware keeps the station config only in RAM, not
+    // flash, so there are no stored profiles to clear: what it remembers is
+    // the last network it was given, which it goes back to whenever the
+    // connection drops. Disconnecting explicitly stops that, and resetting
+    // the chip clears it for good. Credentials saved with
+    // wifi_connect_and_save are the caller’s to clear from their store.
+    pub fn forget_networks(&mut self) -> Result<(), Error<SpiError>> {
+        self.wifi_disconnect()
+    }
+
+    // Leaves the current network, e.g. before joining a different one, and
+    // waits up to 2s for the chip to report Disconnected. Fails with
+    // Error::ConnectionTimeout if it doesn’t.
+    pub fn wifi_disconnect(&mut self) -> Result<(), Error<SpiError>> {
+        self.send_and_receive(
+            NinaCommand::Disconnect,
+            // The firmware ignores it, but wants a param all the same.
+            Params::of(&mut [SendParam::Byte(Self::DUMMY_DATA)]),
+            Params::of(&mut [RecvParam::Ack]),
+        )?;
+
+        for _ in 0..20 {
+            if self.wifi_status()? == WifiStatus::Disconnected {
+                return Ok(());
             }
 
-            self.delay.delay_ms(1000);
+            self.delay.delay_ms(100);
         }
 
-        Err(Error::ConnectionFailed(last_status))
+        Err(Error::ConnectionTimeout)
+    }
+
+    // Connects, and once that succeeds saves the credentials to the store so
+    // that wifi_connect_saved can use them after a reboot.
+    pub fn wifi_connect_and_save<S: CredentialStore>(
+        &mut self,
+        store: &mut S,
+        ssid: &str,
+        password: Option<&str>,
+    ) -> Result<WifiStatus, Error<SpiError>> {
+        let credentials = Credentials::new(ssid, password).ok_or(Error::CredentialsTooLong)?;
+
+        let status = self.wifi_connect(ssid, password)?;
+
+        store
+            .save(&credentials)
+            .map_err(|_| Error::CredentialStoreError)?;
+
+        Ok(status)
+    }
+
+    // Connects using whatever credentials were last saved to the store.
+    pub fn wifi_connect_saved<S: CredentialStore>(
+        &mut self,
+        store: &mut S,
+    ) -> Result<WifiStatus, Error<SpiError>> {
+        let credentials = store
+            .load()
+            .map_err(|_| Error::CredentialStoreError)?
+            .ok_or(Error::NoSavedCredentials)?;
+
+        self.wifi_connect(credentials.ssid(), credentials.password())
+    }
+
+    // Hands the network credentials to the chip and returns without waiting
+    // for it to join. Follow up with poll_connect until it stops returning
+    // WouldBlock, or just stop polling to abandon the attempt.
+    pub fn begin_connect(
+        &mut self,
+        ssid: &str,
+        password: Option<&str>,
+    ) -> Result<(), Error<SpiError>> {
+        match password {
+            None => self.send_and_receive(
+                NinaCommand::SetNetwork,
+                Params::of(&mut [SendParam::Bytes(&mut ssid.bytes())]),
+                Params::of(&mut [RecvParam::Ack]),
+            ),
+
+            Some(password) => self.send_and_receive(
+                NinaCommand::SetNetworkAndPassphrase,
+                Params::of(&mut [
+                    SendParam::Bytes(&mut ssid.bytes()),
+                    SendParam::Bytes(&mut password.bytes()),
+                ]),
+                Params::of(&mut [RecvParam::Ack]),
+            ),
+        }
+    }
+
+    // Checks on a connection started with begin_connect.
+    pub fn poll_connect(&mut self) -> Result<WifiStatus, nb::Error<Error<SpiError>>> {
+        let status = self.wifi_status()?;
+        self.connect_progress(status)
+    }
+
+    // The chip passes through several statuses while it joins (including
+    // NoSsidAvailable while it’s still looking), so only Connected and
+    // ConnectFailed are treated as final.
+    fn connect_progress(
+        &mut self,
+        status: WifiStatus,
+    ) -> Result<WifiStatus, nb::Error<Error<SpiError>>> {
+        match status {
+            WifiStatus::Connected => Ok(status),
+            WifiStatus::ConnectFailed => Err(nb::Error::Other(self.connection_failed(status))),
+            _ => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    fn connection_failed(&mut self, status: WifiStatus) -> Error<SpiError> {
+        Error::ConnectionFailed {
+            status,
+            // Firmware too old to have GetReasonCode answers with an error,
+            // which shouldn’t hide the failure we’re actually reporting.
+            reason: self.wifi_reason_code().ok(),
+        }
+    }
+
+    // Why the chip last disconnected or failed to connect. These are the
+    // ESP32’s reason codes: the 802.11 ones (e.g. 15, the 4-way handshake
+    // timing out, which usually means a wrong password) plus its own from 200
+    // up (201 no AP found, 202 authentication failed, 204 handshake failed).
+    pub fn wifi_reason_code(&mut self) -> Result<u8, Error<SpiError>> {
+        let mut reason: u8 = 0;
+
+        self.send_and_receive(
+            NinaCommand::GetReasonCode,
+            Params::none(),
+            Params::of(&mut [RecvParam::Byte(&mut reason)]),
+        )?;
+
+        Ok(reason)
+    }
+
+    // Asks the chip to start scanning for networks. Results are read with
+    // scan_network_count and the other scan_* methods.
+    //
+    // nina-fw only acknowledges StartScanNetworks; the scan itself runs when
+    // ScanNetworks is sent, which waits for it to finish (a few seconds)
+    // before answering. So the results can be read as soon as this returns,
+    // and they’re complete once they arrive.
+    pub fn begin_scan(&mut self) -> Result<(), Error<SpiError>> {
+        self.send_and_receive(
+            NinaCommand::StartScanNetworks,
+            Params::none(),
+            Params::of(&mut [RecvParam::Ack]),
+        )?;
+
+        self.scan_started = true;
+
+        Ok(())
+    }
+
+    // Whether the results of a scan can be read yet, i.e. whether begin_scan
+    // has been acknowledged (since the last reset). nina-fw can’t scan in
+    // the background, so there’s nothing to wait for after that: reading the
+    // results blocks until the scan is done (see begin_scan). This is so
+    // that `begin_scan()?; while !scan_complete()? {}` ends, and it doesn’t
+    // talk to the chip.
+    pub fn scan_complete(&mut self) -> Result<bool, Error<SpiError>> {
+        Ok(self.scan_started)
+    }
+
+    // Runs a scan and returns how many networks it found. This sends
+    // ScanNetworks, which blocks for the few seconds the scan takes and
+    // renumbers the indexes that scan_rssi and friends take, so call it once
+    // per scan rather than to re-check a count. ScanNetworks responds with
+    // one SSID param per network, so we just count them rather than keeping
+    // the names.
+    pub fn scan_network_count(&mut self) -> Result<u8, Error<SpiError>> {
+        let mut count: usize = 0;
+
+        self.send_and_receive(
+            NinaCommand::ScanNetworks,
+            Params::none(),
+            Params::of(&mut [RecvParam::BufferList(&mut [], &mut [], &mut count)]),
+        )?;
+
+        self.scan_count = count as u8;
+
+        Ok(self.scan_count)
+    }
+
+    // Scans and returns the SSIDs found, in the firmware’s order, so that the
+    // position of each is its index for scan_rssi and friends. Networks past
+    // the first N are left out (but still counted for those indexes), and an
+    // SSID longer than L bytes fails with Error::MismatchedParamSize, so L
+    // should usually be MAX_SSID_LEN.
+    pub fn scan_networks<const N: usize, const L: usize>(
+        &mut self,
+    ) -> Result<heapless::Vec<heapless::String<L>, N>, Error<SpiError>> {
+        self.begin_scan()?;
+
+        let mut storage = [[0u8; L]; N];
+        let mut lens = [0usize; N];
+        let mut count: usize = 0;
+
+        let mut storage_bufs = storage.iter_mut();
+        let mut bufs: [&mut [u8]; N] =
+            core::array::from_fn(|_| &mut storage_bufs.next().unwrap()[..]);
+
+        // Waits for the scan to finish, so no SSIDs means none were found.
+        self.send_and_receive(
+            NinaCommand::ScanNetworks,
+            Params::none(),
+            Params::of(&mut [RecvParam::BufferList(&mut bufs, &mut lens, &mut count)]),
+        )?;
+
+        self.scan_count = count as u8;
+
+        let mut ssids = heapless::Vec::new();
+
+        for (buf, &len) in storage.iter().zip(lens.iter()).take(count) {
+            let ssid = core::str::from_utf8(&buf[..len]).map_err(|_| Error::InvalidUtf8)?;
+
+            let mut out = heapless::String::new();
+            // Can’t fail: len is at most L.
+            let _ = out.push_str(ssid);
+            let _ = ssids.push(out);
+        }
+
+        Ok(ssids)
+    }
+
+    // Runs a fresh scan and returns how many networks it found, so that the
+    // scan_rssi values for those indexes are up to date, e.g. for sampling
+    // signal strength over time. Blocks for as long as the scan takes.
+    pub fn rescan(&mut self) -> Result<u8, Error<SpiError>> {
+        self.begin_scan()?;
+        self.scan_network_count()
+    }
+
+    // Signal strength, in dBm, of the network at the given index of the last
+    // scan. The indexes are positions in the firmware’s list of results, the
+    // same order scan_networks returns the SSIDs in. Before there has been a
+    // scan the firmware has no list to look in, and answers with an error,
+    // i.e. Error::ErrorResponse.
+    pub fn scan_rssi(&mut self, index: u8) -> Result<i32, Error<SpiError>> {
+        let mut rssi: i32 = 0;
+
+        // The firmware copies out its int32 as-is, so this is little-endian.
+        self.send_and_receive(
+            NinaCommand::GetIdxRssi,
+            Params::of(&mut [SendParam::Byte(index)]),
+            Params::of(&mut [RecvParam::LESignedDWord(&mut rssi)]),
+        )?;
+
+        Ok(rssi)
+    }
+
+    // Encryption of the network at the given index of the last scan, with
+    // the same indexes as scan_rssi.
+    pub fn scan_encryption_type(&mut self, index: u8) -> Result<EncryptionType, Error<SpiError>> {
+        let mut enc: u8 = 0;
+
+        self.send_and_receive(
+            NinaCommand::GetIdxEnct,
+            Params::of(&mut [SendParam::Byte(index)]),
+            Params::of(&mut [RecvParam::Byte(&mut enc)]),
+        )?;
+
+        let version = self.firmware()?;
+
+        Ok(EncryptionType::from_firmware(version, enc))
+    }
+
+    // SSID of the network we’re connected to.
+    pub fn current_ssid<const N: usize>(&mut self) -> Result<heapless::String<N>, Error<SpiError>> {
+        let mut buf = [0u8; MAX_SSID_LEN];
+        let mut len: usize = 0;
+
+        self.send_and_receive(
+            NinaCommand::GetCurrentSsid,
+            Params::of(&mut [SendParam::Byte(Self::DUMMY_DATA)]),
+            Params::of(&mut [RecvParam::Buffer(&mut buf, &mut len)]),
+        )?;
+
+        let ssid = core::str::from_utf8(&buf[..len]).map_err(|_| Error::InvalidUtf8)?;
+
+        let mut out = heapless::String::new();
+        out.push_str(ssid)
+            .map_err(|_| Error::MismatchedParamSize(N, len))?;
+
+        Ok(out)
+    }
+
+    // Signal strength of the current connection, in dBm.
+    pub fn current_rssi(&mut self) -> Result<i32, Error<SpiError>> {
+        let mut rssi: i32 = 0;
+
+        self.send_and_receive(
+            NinaCommand::GetCurrentRssi,
+            Params::of(&mut [SendParam::Byte(Self::DUMMY_DATA)]),
+            Params::of(&mut [RecvParam::LESignedDWord(&mut rssi)]),
+        )?;
+
+        Ok(rssi)
+    }
+
+    // Encryption the current network uses. An open network is
+    // EncryptionType::None; bytes the firmware sends that we don’t know come
+    // back as EncryptionType::Unknown rather than failing.
+    pub fn current_encryption_type(&mut self) -> Result<EncryptionType, Error<SpiError>> {
+        let mut enc: u8 = 0;
+
+        self.send_and_receive(
+            NinaCommand::GetCurrentEnct,
+            Params::of(&mut [SendParam::Byte(Self::DUMMY_DATA)]),
+            Params::of(&mut [RecvParam::Byte(&mut enc)]),
+        )?;
+
+        let version = self.firmware()?;
+
+        Ok(EncryptionType::from_firmware(version, enc))
+    }
+
+    // MAC address of the access point we’re connected to.
+    pub fn current_bssid(&mut self) -> Result<[u8; 6], Error<SpiError>> {
+        let mut bssid = [0u8; 6];
+
+        self.send_and_receive(
+            NinaCommand::GetCurrentBssid,
+            Params::of(&mut [SendParam::Byte(Self::DUMMY_DATA)]),
+            Params::of(&mut [RecvParam::ByteArray(&mut bssid)]),
+        )?;
+
+        // The firmware sends the bytes last-to-first.
+        bssid.reverse();
+
+        Ok(bssid)
+    }
+
+    // The firmware has GetCurrentBssid but no command at all for the channel
+    // of the current connection, so this finds our access point in the
+    // results of the last scan and uses the channel recorded there. Returns
+    // None if it isn’t in them (or there hasn’t been a scan); it’s up to the
+    // caller whether to rescan, which blocks for a few seconds and renumbers
+    // the scan indexes. Fails with Error::NotConnected if we aren’t
+    // associated.
+    pub fn current_channel(&mut self) -> Result<Option<u8>, Error<SpiError>> {
+        let bssid = self.current_bssid()?;
+
+        if bssid == [0; 6] {
+            return Err(Error::NotConnected);
+        }
+
+        self.find_scanned_channel(bssid)
+    }
+
+    fn find_scanned_channel(&mut self, bssid: [u8; 6]) -> Result<Option<u8>, Error<SpiError>> {
+        for index in 0..self.scan_count {
+            if self.scan_bssid(index)? == bssid {
+                return self.scan_channel(index).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Collects all of the current_* values in one go, for status displays.
+    pub fn link_info(&mut self) -> Result<LinkInfo, Error<SpiError>> {
+        let bssid = self.current_bssid()?;
+        let channel = self.find_scanned_channel(bssid)?;
+
+        Ok(LinkInfo {
+            ssid: self.current_ssid()?,
+            rssi: self.current_rssi()?,
+            encryption_type: self.current_encryption_type()?,
+            bssid,
+            channel,
+        })
+    }
+
+    // MAC address of the access point at the given index of the last scan.
+    pub fn scan_bssid(&mut self, index: u8) -> Result<[u8; 6], Error<SpiError>> {
+        let mut bssid = [0u8; 6];
+
+        self.send_and_receive(
+            NinaCommand::GetIdxBssid,
+            Params::of(&mut [SendParam::Byte(index)]),
+            Params::of(&mut [RecvParam::ByteArray(&mut bssid)]),
+        )?;
+
+        bssid.reverse();
+
+        Ok(bssid)
+    }
+
+    pub fn scan_channel(&mut self, index: u8) -> Result<u8, Error<SpiError>> {
+        let mut channel: u8 = 0;
+
+        self.send_and_receive(
+            NinaCommand::GetIdxChannel,
+            Params::of(&mut [SendParam::Byte(index)]),
+            Params::of(&mut [RecvParam::Byte(&mut channel)]),
+        )?;
+
+        Ok(channel)
     }
 
     pub fn wifi_create_ap(&mut self, name: &str, channel: u8) -> Result<(), Error<SpiError>> {
@@ -123,4 +907,384 @@ where
             Params::of(&mut [RecvParam::Ack]),
         )
     }
+
+    // Like wifi_create_ap, but the access point is secured with WPA2 using
+    // the given passphrase.
+    pub fn wifi_create_ap_with_password(
+        &mut self,
+        name: &str,
+        password: &str,
+        channel: u8,
+    ) -> Result<(), Error<SpiError>> {
+        // The ESP32 won’t start a WPA2 access point with anything else, and
+        // the firmware doesn’t check for us.
+        if !(8..=63).contains(&password.len()) {
+            return Err(Error::InvalidPassphrase);
+        }
+
+        self.send_and_receive(
+            NinaCommand::SetApPassphrase,
+            Params::of(&mut [
+                SendParam::Bytes(&mut name.bytes()),
+                SendParam::Bytes(&mut password.bytes()),
+                SendParam::Byte(channel),
+            ]),
+            Params::of(&mut [RecvParam::Ack]),
+        )
+    }
+
+    // Whether any station has joined the access point made by
+    // wifi_create_ap. The firmware doesn’t report how many have, only that
+    // its status moved from ApListening to ApConnected, so there’s no
+    // client count to go with this.
+    pub fn ap_has_clients(&mut self) -> Result<bool, Error<SpiError>> {
+        Ok(self.wifi_status()? == WifiStatus::ApConnected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+
+    #[test]
+    fn mac_address_is_reversed_into_transmission_order() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut().respond_with(
+            NinaCommand::GetMacAddress,
+            &[&[0x66, 0x55, 0x44, 0x33, 0x22, 0x11]],
+        );
+
+        let mac = wifi.mac_address().unwrap();
+
+        assert_eq!(mac, [0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+        // Header, then the dummy param.
+        assert_eq!(&bus.borrow().sent[..5], &[0xE0, 0x22, 1, 1, 0xFF]);
+    }
+
+    #[test]
+    fn current_bssid_is_reversed_into_the_usual_order() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut().respond_with(
+            NinaCommand::GetCurrentBssid,
+            &[&[0x66, 0x55, 0x44, 0x33, 0x22, 0x11]],
+        );
+
+        let bssid = wifi.current_bssid().unwrap();
+
+        assert_eq!(bssid, [0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+        assert_eq!(&bus.borrow().sent[..5], &[0xE0, 0x24, 1, 1, 0xFF]);
+    }
+
+    #[test]
+    fn current_channel_looks_in_the_last_scan() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let ours: &[u8] = &[0x66, 0x55, 0x44, 0x33, 0x22, 0x11];
+
+        // No scan yet, and it doesn’t run one.
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetCurrentBssid, &[ours]);
+        assert_eq!(wifi.current_channel().unwrap(), None);
+        assert_eq!(bus.borrow().sent, [0xE0, 0x24, 1, 1, 0xFF, 0xEE, 0, 0]);
+
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::StartScanNetworks, &[&[1]]);
+            bus.respond_with(NinaCommand::ScanNetworks, &[b"other", b"home"]);
+            bus.respond_with(NinaCommand::GetCurrentBssid, &[ours]);
+            bus.respond_with(NinaCommand::GetIdxBssid, &[&[0xAA; 6]]);
+            bus.respond_with(NinaCommand::GetIdxBssid, &[ours]);
+            bus.respond_with(NinaCommand::GetIdxChannel, &[&[11]]);
+
+            // Then, not associated at all.
+            bus.respond_with(NinaCommand::GetCurrentBssid, &[&[0; 6]]);
+        }
+
+        wifi.rescan().unwrap();
+        assert_eq!(wifi.current_channel().unwrap(), Some(11));
+        assert!(matches!(wifi.current_channel(), Err(Error::NotConnected)));
+    }
+
+    #[test]
+    fn current_ssid_rejects_invalid_utf8() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetCurrentSsid, &[b"home"]);
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetCurrentSsid, &[&[b'h', 0xFF]]);
+
+        let ssid: heapless::String<32> = wifi.current_ssid().unwrap();
+        assert_eq!(ssid.as_str(), "home");
+
+        let result = wifi.current_ssid::<32>();
+        assert!(matches!(result, Err(Error::InvalidUtf8)));
+    }
+
+    #[test]
+    fn current_rssi_is_a_signed_little_endian_dword() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetCurrentRssi, &[&(-65i32).to_le_bytes()]);
+
+        assert_eq!(wifi.current_rssi().unwrap(), -65);
+    }
+
+    #[test]
+    fn current_encryption_type_keeps_unknown_bytes() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetCurrentEnct, &[&[9]]);
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetFirmwareVersion, &[b"1.7.4\0"]);
+
+        assert_eq!(
+            wifi.current_encryption_type().unwrap(),
+            EncryptionType::Unknown(9)
+        );
+    }
+
+    #[test]
+    fn wifi_connect_gives_up_as_soon_as_the_connection_fails() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SetNetworkAndPassphrase, &[&[1]]);
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetConnectionStatus, &[&[4]]);
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetReasonCode, &[&[202]]);
+
+        let result = wifi.wifi_connect_with_timeout("home", Some("wrong"), 30, 1000);
+
+        assert!(matches!(
+            result,
+            Err(Error::ConnectionFailed {
+                status: WifiStatus::ConnectFailed,
+                reason: Some(202),
+            })
+        ));
+    }
+
+    #[test]
+    fn wifi_disconnect_waits_for_disconnected() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::Disconnect, &[&[1]]);
+            bus.respond_with(NinaCommand::GetConnectionStatus, &[&[3]]);
+            bus.respond_with(NinaCommand::GetConnectionStatus, &[&[6]]);
+        }
+
+        wifi.wifi_disconnect().unwrap();
+
+        let sent = &bus.borrow().sent;
+        // The dummy param, then two status polls.
+        assert_eq!(&sent[..8], &[0xE0, 0x30, 1, 1, 0xFF, 0xEE, 0, 0]);
+        assert_eq!(&sent[8..], &[0xE0, 0x20, 0, 0xEE, 0xE0, 0x20, 0, 0xEE]);
+    }
+
+    #[test]
+    fn wifi_disconnect_times_out_if_still_connected() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::Disconnect, &[&[1]]);
+        for _ in 0..20 {
+            bus.borrow_mut()
+                .respond_with(NinaCommand::GetConnectionStatus, &[&[3]]);
+        }
+
+        assert!(matches!(
+            wifi.wifi_disconnect(),
+            Err(Error::ConnectionTimeout)
+        ));
+        assert_eq!(bus.borrow().sent.len(), 8 + 20 * 4);
+    }
+
+    #[test]
+    fn wifi_connect_enterprise_enables_then_joins() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::SetEnterpriseIdent, &[&[1]]);
+            bus.respond_with(NinaCommand::SetEnterpriseUsername, &[&[1]]);
+            bus.respond_with(NinaCommand::SetEnterprisePassword, &[&[1]]);
+            bus.respond_with(NinaCommand::SetEnterpriseEnable, &[&[1]]);
+            bus.respond_with(NinaCommand::SetNetwork, &[&[1]]);
+            bus.respond_with(NinaCommand::GetConnectionStatus, &[&[0]]);
+            bus.respond_with(NinaCommand::GetConnectionStatus, &[&[3]]);
+        }
+
+        let status = wifi
+            .wifi_connect_enterprise("corp", "id", "user", "pw")
+            .unwrap();
+        assert_eq!(status, WifiStatus::Connected);
+
+        let expected: &[&[u8]] = &[
+            &[0xE0, 0x4A, 1, 2, b'i', b'd', 0xEE, 0],
+            &[0xE0, 0x4B, 1, 4, b'u', b's', b'e', b'r', 0xEE, 0, 0, 0],
+            &[0xE0, 0x4C, 1, 2, b'p', b'w', 0xEE, 0],
+            // No params.
+            &[0xE0, 0x4F, 0, 0xEE],
+            &[0xE0, 0x10, 1, 4, b'c', b'o', b'r', b'p', 0xEE, 0, 0, 0],
+            &[0xE0, 0x20, 0, 0xEE],
+            &[0xE0, 0x20, 0, 0xEE],
+        ];
+        assert_eq!(bus.borrow().sent, expected.concat());
+    }
+
+    #[test]
+    fn ap_passphrases_must_be_8_to_63_characters() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let bytes = [b'p'; 64];
+        let long = core::str::from_utf8(&bytes).unwrap();
+
+        for password in ["1234567", long] {
+            let result = wifi.wifi_create_ap_with_password("ap", password, 6);
+            assert!(matches!(result, Err(Error::InvalidPassphrase)));
+        }
+        assert!(bus.borrow().sent.is_empty());
+
+        for password in ["12345678", &long[..63]] {
+            bus.borrow_mut().sent.clear();
+            bus.borrow_mut()
+                .respond_with(NinaCommand::SetApPassphrase, &[&[1]]);
+
+            wifi.wifi_create_ap_with_password("ap", password, 6)
+                .unwrap();
+
+            let sent = &bus.borrow().sent;
+            let len = password.len();
+            assert_eq!(&sent[..6], &[0xE0, 0x19, 3, 2, b'a', b'p']);
+            assert_eq!(sent[6] as usize, len);
+            assert_eq!(&sent[7..7 + len], password.as_bytes());
+            assert_eq!(&sent[7 + len..10 + len], &[1, 6, 0xEE]);
+        }
+    }
+
+    #[test]
+    fn wep_keys_must_be_5_or_13_bytes() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let key = [0x4Bu8; 14];
+
+        for len in [0, 4, 14] {
+            let result = wifi.wifi_connect_wep("old", 0, &key[..len]);
+            assert!(matches!(result, Err(Error::InvalidKey)), "len {}", len);
+        }
+        assert!(bus.borrow().sent.is_empty());
+
+        for len in [5, 13] {
+            bus.borrow_mut().sent.clear();
+            bus.borrow_mut().respond_with(NinaCommand::SetKey, &[&[1]]);
+            bus.borrow_mut()
+                .respond_with(NinaCommand::GetConnectionStatus, &[&[3]]);
+
+            let status = wifi.wifi_connect_wep("old", 2, &key[..len]).unwrap();
+            assert_eq!(status, WifiStatus::Connected);
+
+            let sent = &bus.borrow().sent;
+            assert_eq!(
+                &sent[..10],
+                &[0xE0, 0x12, 3, 3, b'o', b'l', b'd', 1, 2, len as u8]
+            );
+            assert_eq!(&sent[10..10 + len], &key[..len]);
+            assert_eq!(sent[10 + len], 0xEE);
+        }
+    }
+
+    #[test]
+    fn ensure_connected_leaves_a_live_connection_alone() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetConnectionStatus, &[&[3]]);
+
+        let status = wifi.ensure_connected("home", Some("secret")).unwrap();
+
+        assert_eq!(status, WifiStatus::Connected);
+        assert_eq!(bus.borrow().sent, [0xE0, 0x20, 0, 0xEE]);
+    }
+
+    #[test]
+    fn ensure_connected_rejoins_after_a_drop() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::GetConnectionStatus, &[&[5]]);
+            bus.respond_with(NinaCommand::SetNetworkAndPassphrase, &[&[1]]);
+            bus.respond_with(NinaCommand::GetConnectionStatus, &[&[3]]);
+        }
+
+        let status = wifi.ensure_connected("home", Some("pw")).unwrap();
+
+        assert_eq!(status, WifiStatus::Connected);
+        let sent = &bus.borrow().sent;
+        assert_eq!(
+            &sent[4..16],
+            &[0xE0, 0x11, 2, 4, b'h', b'o', b'm', b'e', 2, b'p', b'w', 0xEE]
+        );
+    }
+
+    #[test]
+    fn scan_networks_keeps_the_first_n_ssids() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::StartScanNetworks, &[&[1]]);
+        bus.borrow_mut()
+            .respond_with(NinaCommand::ScanNetworks, &[b"home", b"cafe", b"lab"]);
+
+        let ssids = wifi.scan_networks::<2, MAX_SSID_LEN>().unwrap();
+
+        assert_eq!(ssids.len(), 2);
+        assert_eq!(ssids[0].as_str(), "home");
+        assert_eq!(ssids[1].as_str(), "cafe");
+        assert_eq!(wifi.scan_count, 3);
+    }
+
+    #[test]
+    fn scan_networks_with_nothing_in_range_is_empty() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::StartScanNetworks, &[&[1]]);
+        bus.borrow_mut()
+            .respond_with(NinaCommand::ScanNetworks, &[]);
+
+        let ssids = wifi.scan_networks::<4, MAX_SSID_LEN>().unwrap();
+
+        assert!(ssids.is_empty());
+        assert_eq!(wifi.scan_count, 0);
+        // Asked once, not retried.
+        assert_eq!(
+            bus.borrow().sent,
+            [0xE0, 0x36, 0, 0xEE, 0xE0, 0x27, 0, 0xEE]
+        );
+    }
+
+    #[test]
+    fn scan_complete_once_begin_scan_is_acknowledged() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        assert!(!wifi.scan_complete().unwrap());
+
+        bus.borrow_mut()
+            .respond_with(NinaCommand::StartScanNetworks, &[&[1]]);
+        wifi.begin_scan().unwrap();
+
+        // The loop from the scan_complete docs ends straight away, without any
+        // more traffic.
+        let sent = bus.borrow().sent.len();
+        while !wifi.scan_complete().unwrap() {}
+        assert_eq!(bus.borrow().sent.len(), sent);
+
+        // Nothing left to read after a reset.
+        wifi.reset(&mut mock::MockPin).unwrap();
+        assert!(!wifi.scan_complete().unwrap());
+    }
+
+    #[test]
+    fn decodes_encryption_type_by_firmware_version() {
+        let old = FirmwareVersion::parse("1.1.0").unwrap();
+        let new = FirmwareVersion::parse("1.7.4").unwrap();
+
+        assert_eq!(EncryptionType::from_firmware(old, 3), EncryptionType::Wpa2);
+        assert_eq!(EncryptionType::from_firmware(old, 4), EncryptionType::Auto);
+        assert_eq!(EncryptionType::from_firmware(new, 4), EncryptionType::Wpa2);
+        assert_eq!(EncryptionType::from_firmware(new, 7), EncryptionType::None);
+    }
 }
@@ -0,0 +1,80 @@
+// Knobs for talking to NINA firmware that doesn’t behave like the version
+// this crate was written against. The defaults match current firmware.
+#[derive(Debug, Clone)]
+pub struct Config {
+    // Overrides the length encoding used by the TCP data commands
+    // (SendDataTcp and GetDatabufTcp). Current firmware uses 16-bit param
+    // lengths for these, but some older builds use 8-bit lengths. `None`
+    // keeps the per-command default.
+    pub data_param_length: Option<ParamLength>,
+
+    // After this many commands in a row fail because the busy pin never
+    // signalled ready, assume the chip has crashed and fail every command
+    // immediately with Error::DeviceWedged until WifiNina::reset is called.
+    // `None` keeps waiting out the full timeout each time.
+    pub wedged_after_timeouts: Option<u8>,
+
+    // Microseconds to pause after each byte sent or received, for boards where
+    // the NINA is on the far end of a long or noisy trace and back-to-back
+    // bytes get corrupted. Zero (the default) sends bytes at full speed.
+    pub inter_byte_delay_us: u16,
+
+    // Reads the data of variable-length params (socket data, mostly) in
+    // back-to-back bursts of this many bytes, with inter_byte_delay_us only
+    // between bursts, for firmware whose SPI DMA copes better with fixed-size
+    // blocks than with paced single bytes. Bursts start at the start of the
+    // data, so only the last one can be short. 1 (the default) paces every
+    // byte.
+    pub read_burst_len: u8,
+
+    // Requests are padded with zeros after their End byte out to a multiple
+    // of this many bytes. Stock firmware expects 4 (the default); 0 or 1
+    // sends no padding.
+    pub command_alignment: u8,
+
+    // Keeps CS asserted from the start of a command until the end of its
+    // response, rather than releasing it in between. Stock NINA firmware
+    // relies on CS going high to know the request is complete, so only set
+    // this for firmware that expects a single transaction.
+    pub hold_chip_select: bool,
+
+    // How long socket_open and connect wait for a connection to be
+    // established. Calls that take their own timeout ignore this.
+    pub connect_timeout_ms: u16,
+
+    // How long socket_read_timeout and accept wait for something to arrive
+    // when they aren’t given a timeout of their own, and how long
+    // socket_write_all waits for the chip to make room.
+    pub read_timeout_ms: u16,
+
+    // Has network_info, resolve_host_name and opening a socket check first
+    // that we’re connected to a network, failing with Error::NotConnected
+    // if not, instead of sending a command the firmware can’t make sense of.
+    // Costs an extra status command each time, and would get in the way in
+    // AP mode, so it’s off by default.
+    pub require_connected: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            data_param_length: None,
+            wedged_after_timeouts: None,
+            inter_byte_delay_us: 0,
+            read_burst_len: 1,
+            command_alignment: 4,
+            hold_chip_select: false,
+            connect_timeout_ms: 3_000,
+            read_timeout_ms: 1_000,
+            require_connected: false,
+        }
+    }
+}
+
+// Size of the length prefix that precedes each param in a command or
+// response.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ParamLength {
+    U8,
+    U16,
+}
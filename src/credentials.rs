@@ -0,0 +1,75 @@
+use core::fmt::Debug;
+
+// Longest SSID allowed by 802.11.
+pub const MAX_SSID_LEN: usize = 32;
+// Longest WPA passphrase (64 characters is a raw hex PSK).
+pub const MAX_PASSWORD_LEN: usize = 64;
+
+// A network name and optional password, in fixed-size buffers so that they
+// can be loaded from storage without allocating.
+#[derive(Clone)]
+pub struct Credentials {
+    ssid: [u8; MAX_SSID_LEN],
+    ssid_len: usize,
+    password: [u8; MAX_PASSWORD_LEN],
+    // None for open networks.
+    password_len: Option<usize>,
+}
+
+impl Credentials {
+    // Returns None if either value is too long to fit.
+    pub fn new(ssid: &str, password: Option<&str>) -> Option<Self> {
+        if ssid.len() > MAX_SSID_LEN || password.map_or(0, str::len) > MAX_PASSWORD_LEN {
+            return None;
+        }
+
+        let mut credentials = Credentials {
+            ssid: [0; MAX_SSID_LEN],
+            ssid_len: ssid.len(),
+            password: [0; MAX_PASSWORD_LEN],
+            password_len: password.map(str::len),
+        };
+
+        credentials.ssid[..ssid.len()].copy_from_slice(ssid.as_bytes());
+
+        if let Some(password) = password {
+            credentials.password[..password.len()].copy_from_slice(password.as_bytes());
+        }
+
+        Some(credentials)
+    }
+
+    pub fn ssid(&self) -> &str {
+        // Can’t fail, since the bytes came from a &str in new.
+        core::str::from_utf8(&self.ssid[..self.ssid_len]).unwrap_or("")
+    }
+
+    pub fn password(&self) -> Option<&str> {
+        self.password_len
+            .map(|len| core::str::from_utf8(&self.password[..len]).unwrap_or(""))
+    }
+}
+
+impl Debug for Credentials {
+    // Leaves the password out so that it doesn’t end up in logs.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("ssid", &self.ssid())
+            .field("has_password", &self.password_len.is_some())
+            .finish()
+    }
+}
+
+// Somewhere to keep network credentials across reboots (flash, EEPROM, …),
+// for use with WifiNina::wifi_connect_and_save and wifi_connect_saved.
+//
+// The store is passed in to those calls rather than kept on the WifiNina so
+// that the driver doesn’t need a lifetime parameter for it.
+pub trait CredentialStore {
+    type Error: Debug;
+
+    // Returns Ok(None) if nothing has been saved yet.
+    fn load(&mut self) -> Result<Option<Credentials>, Self::Error>;
+
+    fn save(&mut self, credentials: &Credentials) -> Result<(), Self::Error>;
+}
@@ -1,40 +1,79 @@
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
+// Every socket wrapper carries all of WifiNina’s type parameters.
+#![allow(clippy::type_complexity)]
 
 mod chip_select;
 pub mod commands;
+pub mod config;
+pub mod credentials;
+pub mod metrics;
+pub mod pool;
 pub mod util;
 
+#[cfg(test)]
+mod mock;
+
 use core::fmt::Debug;
 
 use chip_select::*;
 use commands::{socket::SocketStatus, wifi::WifiStatus};
+use config::Config;
 use embedded_hal::{
     digital::v2::{InputPin, OutputPin},
     spi::FullDuplex,
 };
+use util::timer::PollCounter;
 
-pub struct WifiNina<CsPin, BusyPin, Spi, Delay>
+pub struct WifiNina<CsPin, BusyPin, Spi, Delay, Timer = PollCounter>
 where
     CsPin: OutputPin,
     BusyPin: InputPin,
-    Delay: embedded_hal::blocking::delay::DelayMs<u16>,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    Timer: crate::util::timer::Timer,
 {
     spi: Spi,
     chip_select: WifiNinaChipSelect<Spi, CsPin, BusyPin>,
     delay: Delay,
+    timer: Timer,
+    config: Config,
+
+    // How many networks the last scan found, so that we can look through
+    // its results without scanning again.
+    scan_count: u8,
+
+    // Whether begin_scan has been acknowledged; see WifiNina::scan_complete.
+    scan_started: bool,
+
+    // How many times socket_new has handed out each socket number.
+    socket_generations: [u8; commands::socket::MAX_SOCKETS],
+
+    // Bit n is set while socket n is in use: from socket_new (or accepting a
+    // client onto it) until socket_close.
+    open_sockets: u16,
+
+    read_ahead: commands::socket::ReadAhead,
+
+    // See WifiNina::last_param_count.
+    last_param_count: Option<u8>,
+
+    // Read on first use; see WifiNina::firmware.
+    firmware: Option<commands::diagnostics::FirmwareVersion>,
+
+    #[cfg(feature = "metrics")]
+    metrics: metrics::Metrics,
 }
 
-impl<CsPin, BusyPin, Spi, SpiError, Delay> WifiNina<CsPin, BusyPin, Spi, Delay>
+impl<CsPin, BusyPin, Spi, SpiError, Delay> WifiNina<CsPin, BusyPin, Spi, Delay, PollCounter>
 where
     BusyPin: InputPin,
     CsPin: OutputPin,
     Spi:
         FullDuplex<u8, Error = SpiError> + embedded_hal::blocking::spi::Write<u8, Error = SpiError>,
     SpiError: Debug,
-    Delay: embedded_hal::blocking::delay::DelayMs<u16>, //+ embedded_hal::blocking::spi::WriteIter<u8, Error = SpiError>,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
 {
-    // const ConnectionDelayMs: u16 = 100;
-
     // We take the spi here just to allow the type to be implied.
     //
     // Also resets the WifiNINA chip.
@@ -45,6 +84,36 @@ where
         reset: &mut ResetPin,
         delay: Delay,
     ) -> Result<Self, Error<SpiError>>
+    where
+        ResetPin: OutputPin,
+    {
+        Self::new_with_timer(spi, cs, busy, reset, delay, PollCounter::default())
+    }
+}
+
+impl<CsPin, BusyPin, Spi, SpiError, Delay, Timer> WifiNina<CsPin, BusyPin, Spi, Delay, Timer>
+where
+    BusyPin: InputPin,
+    CsPin: OutputPin,
+    Spi:
+        FullDuplex<u8, Error = SpiError> + embedded_hal::blocking::spi::Write<u8, Error = SpiError>,
+    SpiError: Debug,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>, //+ embedded_hal::blocking::spi::WriteIter<u8, Error = SpiError>,
+    Timer: crate::util::timer::Timer,
+{
+    // const ConnectionDelayMs: u16 = 100;
+
+    // Like new, but times the waits for the chip with the given Timer, e.g. a
+    // util::timer::CountDownTimer, rather than by counting delays.
+    pub fn new_with_timer<ResetPin>(
+        spi: Spi,
+        cs: CsPin,
+        busy: BusyPin,
+        reset: &mut ResetPin,
+        delay: Delay,
+        timer: Timer,
+    ) -> Result<Self, Error<SpiError>>
     where
         ResetPin: OutputPin,
     {
@@ -53,6 +122,17 @@ where
             chip_select: WifiNinaChipSelect::new(cs, busy)
                 .map_err(|_| Error::ChipSelectPinError)?,
             delay,
+            timer,
+            config: Config::default(),
+            scan_count: 0,
+            scan_started: false,
+            socket_generations: [0; commands::socket::MAX_SOCKETS],
+            open_sockets: 0,
+            read_ahead: commands::socket::ReadAhead::new(),
+            last_param_count: None,
+            firmware: None,
+            #[cfg(feature = "metrics")]
+            metrics: metrics::Metrics::default(),
         };
 
         wifi.reset(reset)?;
@@ -61,27 +141,143 @@ where
     }
 
     pub fn reset<ResetPin>(&mut self, reset: &mut ResetPin) -> Result<(), Error<SpiError>>
+    where
+        ResetPin: OutputPin,
+    {
+        self.reset_with(reset, 250, 750)
+    }
+
+    // Like reset, but holds the reset pin low for low_ms and then waits
+    // high_ms for the chip to boot, for boards that need a longer pulse or
+    // boot faster than the defaults allow for.
+    pub fn reset_with<ResetPin>(
+        &mut self,
+        reset: &mut ResetPin,
+        low_ms: u16,
+        high_ms: u16,
+    ) -> Result<(), Error<SpiError>>
     where
         ResetPin: OutputPin,
     {
         reset.set_low().map_err(|_| Error::ResetPinError)?;
 
-        self.delay.delay_ms(250);
+        self.delay.delay_ms(low_ms);
 
         reset.set_high().map_err(|_| Error::ResetPinError)?;
 
-        self.delay.delay_ms(750);
+        self.delay.delay_ms(high_ms);
+
+        self.chip_select.clear_timeouts();
+
+        // The chip may have been reflashed while it was held in reset.
+        self.firmware = None;
+        self.scan_started = false;
+
+        // And it has forgotten every socket, so any Socket still held refers
+        // to a number it will hand out afresh.
+        for generation in self.socket_generations.iter_mut() {
+            *generation = generation.wrapping_add(1);
+        }
+
+        Ok(())
+    }
+
+    // Everything needed to get going again after the chip has wedged: puts CS
+    // back high, resets the chip, discards what we knew about its state and
+    // then checks that it answers a command.
+    pub fn reinit<ResetPin>(&mut self, reset: &mut ResetPin) -> Result<(), Error<SpiError>>
+    where
+        ResetPin: OutputPin,
+    {
+        self.chip_select.release()?;
+
+        self.reset(reset)?;
+
+        // Sockets don’t survive a reset.
+        self.open_sockets = 0;
+        self.read_ahead = commands::socket::ReadAhead::new();
+        self.scan_count = 0;
+
+        self.firmware()?;
 
         Ok(())
     }
 
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    // Returns and clears the error from the last time driving CS high failed
+    // at the end of a command. Those happen after the command has otherwise
+    // finished, so they can’t be returned from it, but a CS line that won’t
+    // deassert garbles the framing of the commands after it.
+    pub fn take_last_error(&mut self) -> Option<Error<SpiError>> {
+        self.chip_select.take_deselect_error().map(Error::from)
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> &metrics::Metrics {
+        &self.metrics
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn reset_metrics(&mut self) {
+        self.metrics = metrics::Metrics::default();
+    }
+
+    // Free without the metrics feature.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub(crate) fn count(&mut self, event: metrics::Event) {
+        #[cfg(feature = "metrics")]
+        self.metrics.record(event);
+    }
+
     // Static method because it needs to be called while device_selector is borrowed
 }
 
+// Shows the driver’s own state and leaves out the peripherals, so that it
+// doesn’t need them to be Debug.
+impl<CsPin, BusyPin, Spi, Delay, Timer> Debug for WifiNina<CsPin, BusyPin, Spi, Delay, Timer>
+where
+    CsPin: OutputPin,
+    BusyPin: InputPin,
+    Delay:
+        embedded_hal::blocking::delay::DelayMs<u16> + embedded_hal::blocking::delay::DelayUs<u16>,
+    Timer: crate::util::timer::Timer,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("WifiNina")
+            .field("config", &self.config)
+            .field("scan_count", &self.scan_count)
+            .field("open_sockets", &OpenSockets(self.open_sockets))
+            .field(
+                "consecutive_timeouts",
+                &self.chip_select.consecutive_timeouts(),
+            )
+            .finish()
+    }
+}
+
+// Prints the open_sockets bitmask as a list of socket numbers.
+struct OpenSockets(u16);
+
+impl Debug for OpenSockets {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list()
+            .entries((0..16).filter(|n| self.0 & (1 << n) != 0))
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 pub enum Error<SpiError: Debug> {
     ChipSelectPinError,
     ChipSelectTimeout,
+    DeviceWedged,
 
     ResponseTimeout,
     MissingParam(u8),
@@ -89,27 +285,85 @@ pub enum Error<SpiError: Debug> {
     MismatchedParamSize(usize, usize),
     ErrorResponse,
     UnexpectedResponse(u8, u8),
+    InvalidUtf8,
+    // The firmware’s version string isn’t major.minor.patch.
+    UnrecognizedFirmwareVersion,
+    // The firmware has no command for the requested operation. Methods for
+    // things the NINA firmware can’t do at all still exist, and always return
+    // this, so that callers can feature-detect at runtime rather than
+    // carrying their own version checks; see also WifiNina::capabilities.
+    Unsupported,
 
-    ConnectionFailed(WifiStatus),
+    // reason is the chip’s last disconnect reason code, if it could be read.
+    // See WifiNina::wifi_reason_code.
+    ConnectionFailed {
+        status: WifiStatus,
+        reason: Option<u8>,
+    },
     ConnectionTimeout,
+    // We aren’t connected to a network, and something needed us to be:
+    // Config::require_connected is set, or current_channel was asked for the
+    // channel of a connection there isn’t.
+    NotConnected,
+
+    CredentialsTooLong,
+    NoSavedCredentials,
+    CredentialStoreError,
+    CertificateTooLong,
+    // A WPA2 passphrase must be 8 to 63 characters.
+    InvalidPassphrase,
+    // A WEP key must be 5 or 13 bytes (WEP-40 or WEP-104).
+    InvalidKey,
+    // The chip didn’t keep a config it was given.
+    ConfigMismatch,
+    // A core::fmt::Write sink we were given to write into failed.
+    FormatError,
+
+    // From resolve_via_udp.
+    InvalidHostname,
+    HostNotFound,
+    InvalidDnsResponse,
 
     SocketConnectionFailed(SocketStatus),
     SocketClosed,
+    // The socket’s number has been handed out again by socket_new since this
+    // Socket was made, so it no longer refers to our connection.
+    SocketReused,
     SocketTimeout,
-    NoSocketAvailable,
+    // A cancel callback asked for the operation to stop.
+    Cancelled,
+    // Holds the number the chip sent instead of a socket; 255 is its usual
+    // way of saying it has none left.
+    NoSocketAvailable(u8),
 
-    SpiError(SpiError),
+    SpiError {
+        phase: Phase,
+        source: SpiError,
+    },
     ResetPinError,
 }
 
+// Which part of a command/response exchange an SPI error happened in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Phase {
+    // Start byte, command byte and param count of a request.
+    SendHeader,
+    // Param lengths and data, end byte and padding of a request.
+    SendBody,
+    // Waiting for the start byte, then the reply command byte and param count.
+    RecvHeader,
+    // Param lengths and data of a response.
+    RecvBody,
+}
+
 impl<SpiError> Error<SpiError>
 where
     SpiError: Debug,
 {
     // Convenience function for passing to map_err, because we can’t use
     // the From trait because SpiError is fully parameterized.
-    fn spi(err: SpiError) -> Error<SpiError> {
-        Error::SpiError(err)
+    fn spi(phase: Phase) -> impl Fn(SpiError) -> Error<SpiError> {
+        move |source| Error::SpiError { phase, source }
     }
 }
 
@@ -0,0 +1,37 @@
+// Counts of the hiccups the driver has recovered from (or reported), for
+// spotting hardware that’s starting to go bad. Only kept with the `metrics`
+// feature; see WifiNina::metrics.
+#[derive(Debug, Default, Clone)]
+pub struct Metrics {
+    // Writes tried again because the chip’s send buffer was full.
+    pub retries: u32,
+    // Commands whose response never started.
+    pub response_timeouts: u32,
+    // Commands that gave up waiting for the busy pin.
+    pub chip_select_timeouts: u32,
+    // Dropped connections reopened on the same socket, by reconnect_socket
+    // or a pool::ConnectionPool.
+    pub reconnects: u32,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum Event {
+    Retry,
+    ResponseTimeout,
+    ChipSelectTimeout,
+    Reconnect,
+}
+
+impl Metrics {
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    pub(crate) fn record(&mut self, event: Event) {
+        let counter = match event {
+            Event::Retry => &mut self.retries,
+            Event::ResponseTimeout => &mut self.response_timeouts,
+            Event::ChipSelectTimeout => &mut self.chip_select_timeouts,
+            Event::Reconnect => &mut self.reconnects,
+        };
+
+        *counter = counter.saturating_add(1);
+    }
+}
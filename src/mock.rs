@@ -0,0 +1,207 @@
+// Stand-ins for the SPI bus, pins and delay, so that the command framing can
+// be checked without a chip attached.
+//
+// MockSpi records every byte the driver sends and answers reads with the
+// responses queued up by the test, one per command. A response starts being
+// served once the command’s End byte has been written, and everything read
+// before that is 0xFF. Bytes clocked out while reading a response don’t count
+// as sent.
+
+extern crate std;
+
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, vec::Vec};
+
+use embedded_hal::{
+    blocking::delay::{DelayMs, DelayUs},
+    digital::v2::{InputPin, OutputPin},
+    spi::FullDuplex,
+};
+
+use crate::{commands::NinaCommand, WifiNina};
+
+#[derive(Default)]
+pub struct Bus {
+    // Everything the driver has written, across all commands.
+    pub sent: Vec<u8>,
+
+    responses: VecDeque<Vec<u8>>,
+    current: VecDeque<u8>,
+    responding: bool,
+
+    cs_low: bool,
+
+    // How many times CS has been driven low.
+    pub selects: usize,
+
+    // How many times the driver has paused with delay_us.
+    pub delays_us: usize,
+
+    // Makes busy read high no matter what, like a chip that has crashed.
+    pub busy_stuck: bool,
+    // How many times the driver has read the busy pin.
+    pub busy_polls: usize,
+}
+
+impl Bus {
+    // Queues the raw bytes the chip should answer the next command with.
+    pub fn respond(&mut self, bytes: &[u8]) {
+        self.responses.push_back(bytes.to_vec());
+    }
+
+    // Queues a well-formed response to cmd with the given params, using
+    // 8-bit param lengths.
+    pub fn respond_with(&mut self, cmd: NinaCommand, params: &[&[u8]]) {
+        let mut bytes = std::vec![
+            NinaCommand::Start.into(),
+            u8::from(cmd) | 0x80,
+            params.len() as u8
+        ];
+
+        for param in params {
+            bytes.push(param.len() as u8);
+            bytes.extend_from_slice(param);
+        }
+
+        bytes.push(NinaCommand::End.into());
+
+        self.respond(&bytes);
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        if bytes.len() == 3 && bytes[0] == NinaCommand::Start.into() {
+            // A new command header, so whatever was left of the last
+            // response has been abandoned.
+            self.responding = false;
+            self.current.clear();
+        }
+
+        // Blocking writes are only used for sending, so keep them even once
+        // the response is armed; that’s where the padding after End goes.
+        self.sent.extend_from_slice(bytes);
+
+        if bytes == [NinaCommand::End.into()] {
+            self.responding = true;
+            self.current = self.responses.pop_front().unwrap_or_default().into();
+        }
+    }
+}
+
+pub type SharedBus = Rc<RefCell<Bus>>;
+
+pub struct MockSpi(pub SharedBus);
+
+impl FullDuplex<u8> for MockSpi {
+    type Error = ();
+
+    fn read(&mut self) -> nb::Result<u8, ()> {
+        let mut bus = self.0.borrow_mut();
+
+        if bus.responding {
+            Ok(bus.current.pop_front().unwrap_or(0xFF))
+        } else {
+            Ok(0xFF)
+        }
+    }
+
+    fn send(&mut self, word: u8) -> nb::Result<(), ()> {
+        let mut bus = self.0.borrow_mut();
+
+        if !bus.responding {
+            bus.sent.push(word);
+        }
+
+        Ok(())
+    }
+}
+
+impl embedded_hal::blocking::spi::Write<u8> for MockSpi {
+    type Error = ();
+
+    fn write(&mut self, words: &[u8]) -> Result<(), ()> {
+        self.0.borrow_mut().write(words);
+        Ok(())
+    }
+}
+
+// CS drives the shared state; busy reads back as its inverse, i.e. the chip
+// is always immediately ready, unless Bus::busy_stuck is set.
+pub struct MockCs(pub SharedBus);
+
+impl OutputPin for MockCs {
+    type Error = ();
+
+    fn set_low(&mut self) -> Result<(), ()> {
+        let mut bus = self.0.borrow_mut();
+        bus.cs_low = true;
+        bus.selects += 1;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), ()> {
+        self.0.borrow_mut().cs_low = false;
+        Ok(())
+    }
+}
+
+pub struct MockBusy(pub SharedBus);
+
+impl InputPin for MockBusy {
+    type Error = ();
+
+    fn is_high(&self) -> Result<bool, ()> {
+        let mut bus = self.0.borrow_mut();
+        bus.busy_polls += 1;
+        Ok(bus.cs_low || bus.busy_stuck)
+    }
+
+    fn is_low(&self) -> Result<bool, ()> {
+        self.is_high().map(|b| !b)
+    }
+}
+
+pub struct MockPin;
+
+impl OutputPin for MockPin {
+    type Error = ();
+
+    fn set_low(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+// Doesn’t actually wait, but counts the microsecond delays (the inter-byte
+// pauses) in Bus::delays_us.
+pub struct MockDelay(pub SharedBus);
+
+impl DelayMs<u16> for MockDelay {
+    fn delay_ms(&mut self, _: u16) {}
+}
+
+impl DelayUs<u16> for MockDelay {
+    fn delay_us(&mut self, _: u16) {
+        self.0.borrow_mut().delays_us += 1;
+    }
+}
+
+pub type MockWifiNina = WifiNina<MockCs, MockBusy, MockSpi, MockDelay>;
+
+// A WifiNina wired up to a fresh Bus, which is returned alongside it for
+// queueing responses and inspecting what was sent.
+pub fn wifi_nina() -> (MockWifiNina, SharedBus) {
+    let bus = SharedBus::default();
+
+    let wifi = WifiNina::new(
+        MockSpi(bus.clone()),
+        MockCs(bus.clone()),
+        MockBusy(bus.clone()),
+        &mut MockPin,
+        MockDelay(bus.clone()),
+    )
+    .unwrap();
+
+    (wifi, bus)
+}
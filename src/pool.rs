@@ -0,0 +1,345 @@
+use core::fmt::Debug;
+
+use embedded_hal::{
+    digital::v2::{InputPin, OutputPin},
+    spi::FullDuplex,
+};
+
+use crate::{
+    commands::socket::{ConnectedSocket, Destination, Protocol, Socket, SocketStatus},
+    metrics::Event,
+    Error, WifiNina,
+};
+
+// Keeps up to N connections open between uses, so that something polling the
+// same server over and over doesn’t pay for DNS and the TCP (or TLS)
+// handshake every time. Connections are looked up by a key of the caller’s
+// choosing, e.g. an enum of the servers the application talks to.
+//
+// Idle time is measured in ticks, which only advance when tick is called, so
+// the caller decides what a tick is (a second, a pass of the main loop, …).
+pub struct ConnectionPool<K, const N: usize> {
+    entries: heapless::Vec<Entry<K>, N>,
+    now: u32,
+}
+
+struct Entry<K> {
+    key: K,
+    socket: Socket,
+    last_used: u32,
+}
+
+impl<K: PartialEq, const N: usize> ConnectionPool<K, N> {
+    pub fn new() -> Self {
+        ConnectionPool {
+            entries: heapless::Vec::new(),
+            now: 0,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.now = self.now.wrapping_add(1);
+    }
+
+    // Returns the pooled connection for `key` if it’s still established, and
+    // otherwise opens one (reusing the pooled socket number if there was a
+    // connection that has since dropped, unless the number has been handed
+    // out again, e.g. after WifiNina::reset). If the pool is full, the
+    // connection that has gone unused the longest is closed to make room.
+    //
+    // The returned ConnectedSocket stays open when dropped; the pool closes
+    // it in close_idle or close_all.
+    pub fn persistent_connect<'w, CS, B, S, SE, D, T>(
+        &mut self,
+        wifi: &'w mut WifiNina<CS, B, S, D, T>,
+        key: K,
+        protocol: Protocol,
+        destination: Destination,
+        port: u16,
+    ) -> Result<ConnectedSocket<'w, CS, B, S, SE, D, T>, Error<SE>>
+    where
+        CS: OutputPin,
+        B: InputPin,
+        S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
+        SE: Debug,
+        D: embedded_hal::blocking::delay::DelayMs<u16>
+            + embedded_hal::blocking::delay::DelayUs<u16>,
+        T: crate::util::timer::Timer,
+    {
+        let now = self.now;
+
+        let found = self.entries.iter().position(|e| e.key == key);
+
+        // The chip no longer knows the number as ours, so there’s nothing
+        // to close; start over with a fresh one.
+        let found = match found {
+            Some(idx) if !wifi.socket_is_current(&self.entries[idx].socket) => {
+                self.entries.swap_remove(idx);
+                None
+            }
+            found => found,
+        };
+
+        if let Some(idx) = found {
+            let entry = &mut self.entries[idx];
+
+            if wifi.socket_status(&entry.socket)? != SocketStatus::Established {
+                wifi.socket_close(&entry.socket).ok();
+
+                // Sets the open bit again, which socket_close cleared.
+                if let Err(err) = wifi.socket_open(&entry.socket, protocol, destination, port) {
+                    self.entries.swap_remove(idx);
+                    return Err(err);
+                }
+
+                wifi.count(Event::Reconnect);
+            }
+
+            entry.last_used = now;
+
+            return Ok(ConnectedSocket::kept_open(
+                wifi,
+                entry.socket.duplicate_unchecked(),
+            ));
+        }
+
+        if self.entries.is_full() {
+            self.close_least_recently_used(wifi)?;
+        }
+
+        let socket = wifi.socket_new()?;
+
+        if let Err(err) = wifi.socket_open(&socket, protocol, destination, port) {
+            wifi.socket_close(&socket).ok();
+            return Err(err);
+        }
+
+        let handle = socket.duplicate_unchecked();
+
+        // Can’t fail: we made room above.
+        let _ = self.entries.push(Entry {
+            key,
+            socket,
+            last_used: now,
+        });
+
+        Ok(ConnectedSocket::kept_open(wifi, handle))
+    }
+
+    // Closes every connection that hasn’t been used in more than
+    // max_idle_ticks ticks.
+    pub fn close_idle<CS, B, S, SE, D, T>(
+        &mut self,
+        wifi: &mut WifiNina<CS, B, S, D, T>,
+        max_idle_ticks: u32,
+    ) -> Result<(), Error<SE>>
+    where
+        CS: OutputPin,
+        B: InputPin,
+        S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
+        SE: Debug,
+        D: embedded_hal::blocking::delay::DelayMs<u16>
+            + embedded_hal::blocking::delay::DelayUs<u16>,
+        T: crate::util::timer::Timer,
+    {
+        let mut idx = 0;
+
+        while idx < self.entries.len() {
+            let idle = self.now.wrapping_sub(self.entries[idx].last_used);
+
+            if idle > max_idle_ticks {
+                let entry = self.entries.swap_remove(idx);
+                wifi.socket_close(&entry.socket)?;
+            } else {
+                idx += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn close_all<CS, B, S, SE, D, T>(
+        &mut self,
+        wifi: &mut WifiNina<CS, B, S, D, T>,
+    ) -> Result<(), Error<SE>>
+    where
+        CS: OutputPin,
+        B: InputPin,
+        S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
+        SE: Debug,
+        D: embedded_hal::blocking::delay::DelayMs<u16>
+            + embedded_hal::blocking::delay::DelayUs<u16>,
+        T: crate::util::timer::Timer,
+    {
+        while let Some(entry) = self.entries.pop() {
+            wifi.socket_close(&entry.socket)?;
+        }
+
+        Ok(())
+    }
+
+    fn close_least_recently_used<CS, B, S, SE, D, T>(
+        &mut self,
+        wifi: &mut WifiNina<CS, B, S, D, T>,
+    ) -> Result<(), Error<SE>>
+    where
+        CS: OutputPin,
+        B: InputPin,
+        S: FullDuplex<u8, Error = SE> + embedded_hal::blocking::spi::Write<u8, Error = SE>,
+        SE: Debug,
+        D: embedded_hal::blocking::delay::DelayMs<u16>
+            + embedded_hal::blocking::delay::DelayUs<u16>,
+        T: crate::util::timer::Timer,
+    {
+        let now = self.now;
+
+        let oldest = self
+            .entries
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, e)| now.wrapping_sub(e.last_used))
+            .map(|(idx, _)| idx);
+
+        if let Some(idx) = oldest {
+            let entry = self.entries.swap_remove(idx);
+            wifi.socket_close(&entry.socket)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<K: PartialEq, const N: usize> Default for ConnectionPool<K, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::NinaCommand, mock};
+
+    const SERVER: Destination = Destination::Ip([10, 0, 0, 1]);
+
+    #[test]
+    fn reuses_established_connections_and_reaps_idle_ones() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let mut pool = ConnectionPool::<u8, 2>::new();
+
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::GetSocket, &[&[5]]);
+            bus.respond_with(NinaCommand::StartClientTcp, &[&[1]]);
+            bus.respond_with(NinaCommand::GetClientStateTcp, &[&[4]]);
+        }
+
+        let num = pool
+            .persistent_connect(&mut wifi, 1, Protocol::TCP, SERVER, 80)
+            .unwrap()
+            .socket()
+            .num();
+        assert_eq!(num, 5);
+
+        // Still established, so it’s only checked on.
+        bus.borrow_mut().sent.clear();
+        bus.borrow_mut()
+            .respond_with(NinaCommand::GetClientStateTcp, &[&[4]]);
+
+        let num = pool
+            .persistent_connect(&mut wifi, 1, Protocol::TCP, SERVER, 80)
+            .unwrap()
+            .socket()
+            .num();
+        assert_eq!(num, 5);
+        assert_eq!(bus.borrow().sent, [0xE0, 0x2F, 1, 1, 5, 0xEE, 0, 0]);
+
+        pool.tick();
+        pool.tick();
+
+        bus.borrow_mut().sent.clear();
+        pool.close_idle(&mut wifi, 2).unwrap();
+        assert!(bus.borrow().sent.is_empty());
+
+        pool.tick();
+
+        bus.borrow_mut()
+            .respond_with(NinaCommand::StopClientTcp, &[&[1]]);
+        pool.close_idle(&mut wifi, 2).unwrap();
+        assert_eq!(bus.borrow().sent, [0xE0, 0x2E, 1, 1, 5, 0xEE, 0, 0]);
+    }
+
+    #[test]
+    fn reopens_a_dropped_connection_on_the_same_socket() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let mut pool = ConnectionPool::<u8, 2>::new();
+
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::GetSocket, &[&[5]]);
+            bus.respond_with(NinaCommand::StartClientTcp, &[&[1]]);
+            bus.respond_with(NinaCommand::GetClientStateTcp, &[&[4]]);
+
+            // Then it has dropped: closed, and opened again.
+            bus.respond_with(NinaCommand::GetClientStateTcp, &[&[0]]);
+            bus.respond_with(NinaCommand::StopClientTcp, &[&[1]]);
+            bus.respond_with(NinaCommand::StartClientTcp, &[&[1]]);
+            bus.respond_with(NinaCommand::GetClientStateTcp, &[&[4]]);
+        }
+
+        pool.persistent_connect(&mut wifi, 1, Protocol::TCP, SERVER, 80)
+            .unwrap();
+
+        let mut conn = pool
+            .persistent_connect(&mut wifi, 1, Protocol::TCP, SERVER, 80)
+            .unwrap();
+        assert_eq!(conn.socket().num(), 5);
+
+        bus.borrow_mut()
+            .respond_with(NinaCommand::SendDataTcp, &[&[2, 0]]);
+        assert_eq!(conn.write(b"hi").unwrap(), 2);
+        drop(conn);
+
+        // Open again, after socket_close cleared it.
+        assert_eq!(wifi.open_sockets, 1 << 5);
+    }
+
+    #[test]
+    fn opens_a_fresh_socket_after_a_reset() {
+        let (mut wifi, bus) = mock::wifi_nina();
+        let mut pool = ConnectionPool::<u8, 2>::new();
+
+        {
+            let mut bus = bus.borrow_mut();
+            bus.respond_with(NinaCommand::GetSocket, &[&[5]]);
+            bus.respond_with(NinaCommand::StartClientTcp, &[&[1]]);
+            bus.respond_with(NinaCommand::GetClientStateTcp, &[&[4]]);
+        }
+
+        pool.persistent_connect(&mut wifi, 1, Protocol::TCP, SERVER, 80)
+            .unwrap();
+
+        wifi.reset(&mut mock::MockPin).unwrap();
+
+        {
+            let mut bus = bus.borrow_mut();
+            bus.sent.clear();
+            bus.respond_with(NinaCommand::GetSocket, &[&[5]]);
+            bus.respond_with(NinaCommand::StartClientTcp, &[&[1]]);
+            bus.respond_with(NinaCommand::GetClientStateTcp, &[&[4]]);
+            bus.respond_with(NinaCommand::SendDataTcp, &[&[2, 0]]);
+        }
+
+        let mut conn = pool
+            .persistent_connect(&mut wifi, 1, Protocol::TCP, SERVER, 80)
+            .unwrap();
+
+        // Straight to GetSocket, without checking on or closing the old one.
+        assert_eq!(&bus.borrow().sent[..4], &[0xE0, 0x3F, 0, 0xEE]);
+        assert_eq!(conn.write(b"hi").unwrap(), 2);
+        drop(conn);
+
+        assert_eq!(pool.entries.len(), 1);
+        assert_eq!(wifi.open_sockets, 1 << 5);
+    }
+}
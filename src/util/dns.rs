@@ -0,0 +1,151 @@
+// Just enough of the DNS wire format (RFC 1035) to ask a server for a name’s
+// IPv4 address over UDP, for WifiNina::resolve_via_udp.
+
+// Header, the longest name we’ll encode, then QTYPE and QCLASS.
+pub const MAX_QUERY_LEN: usize = 12 + MAX_NAME_LEN + 4;
+
+// Longest encoded name: the length bytes, the labels and the final zero.
+const MAX_NAME_LEN: usize = 255;
+
+const TYPE_A: u16 = 1;
+const CLASS_IN: u16 = 1;
+
+#[derive(Debug, PartialEq)]
+pub enum DnsError {
+    // The server answered, but not with an address for the name.
+    NotFound,
+    // The response was cut short, or isn’t a response to our query.
+    Malformed,
+}
+
+// Writes a recursive query for the name’s A records into buf, returning its
+// length. None if the name can’t be encoded: an empty label, a label longer
+// than 63 bytes, or more than 255 bytes in all.
+pub fn encode_query(id: u16, name: &str, buf: &mut [u8; MAX_QUERY_LEN]) -> Option<usize> {
+    buf[0..2].copy_from_slice(&id.to_be_bytes());
+    // Just the RD flag.
+    buf[2..4].copy_from_slice(&[0x01, 0x00]);
+    // One question, no answers or other records.
+    buf[4..12].copy_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    let mut pos = 12;
+
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 || pos + 1 + label.len() >= 12 + MAX_NAME_LEN {
+            return None;
+        }
+
+        buf[pos] = label.len() as u8;
+        buf[pos + 1..pos + 1 + label.len()].copy_from_slice(label.as_bytes());
+        pos += 1 + label.len();
+    }
+
+    buf[pos] = 0;
+    pos += 1;
+
+    buf[pos..pos + 2].copy_from_slice(&TYPE_A.to_be_bytes());
+    buf[pos + 2..pos + 4].copy_from_slice(&CLASS_IN.to_be_bytes());
+
+    Some(pos + 4)
+}
+
+// Finds the first IPv4 address among the answers of a response to the query
+// with the given id.
+pub fn parse_response(id: u16, msg: &[u8]) -> Result<[u8; 4], DnsError> {
+    let header = msg.get(..12).ok_or(DnsError::Malformed)?;
+
+    let is_response = header[2] & 0x80 != 0;
+
+    if header[0..2] != id.to_be_bytes() || !is_response {
+        return Err(DnsError::Malformed);
+    }
+
+    // RCODE, e.g. 3 for a name that doesn’t exist.
+    if header[3] & 0x0F != 0 {
+        return Err(DnsError::NotFound);
+    }
+
+    let questions = u16::from_be_bytes([header[4], header[5]]);
+    let answers = u16::from_be_bytes([header[6], header[7]]);
+
+    let mut pos = 12;
+
+    for _ in 0..questions {
+        pos = skip_name(msg, pos)? + 4;
+    }
+
+    for _ in 0..answers {
+        pos = skip_name(msg, pos)?;
+
+        let fields = msg.get(pos..pos + 10).ok_or(DnsError::Malformed)?;
+        let rtype = u16::from_be_bytes([fields[0], fields[1]]);
+        let class = u16::from_be_bytes([fields[2], fields[3]]);
+        let len = u16::from_be_bytes([fields[8], fields[9]]) as usize;
+        pos += 10;
+
+        let data = msg.get(pos..pos + len).ok_or(DnsError::Malformed)?;
+        pos += len;
+
+        // Anything else is most likely the CNAME chain leading up to it.
+        if rtype == TYPE_A && class == CLASS_IN && len == 4 {
+            return Ok([data[0], data[1], data[2], data[3]]);
+        }
+    }
+
+    Err(DnsError::NotFound)
+}
+
+// Returns the position just past the name starting at pos. Names in answers
+// are usually compressed: a pointer back to the same name in the question.
+fn skip_name(msg: &[u8], mut pos: usize) -> Result<usize, DnsError> {
+    loop {
+        let len = *msg.get(pos).ok_or(DnsError::Malformed)?;
+
+        match len {
+            0 => return Ok(pos + 1),
+            // A pointer is two bytes, and ends the name.
+            len if len & 0xC0 == 0xC0 => return Ok(pos + 2),
+            len => pos += 1 + len as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_address_past_a_compressed_cname() {
+        let mut query = [0u8; MAX_QUERY_LEN];
+        let len = encode_query(0x1234, "www.example.com", &mut query).unwrap();
+
+        assert_eq!(
+            &query[12..len],
+            b"\x03www\x07example\x03com\x00\x00\x01\x00\x01"
+        );
+
+        let mut response = [0u8; 512];
+        response[..len].copy_from_slice(&query[..len]);
+        // A response, with two answers.
+        response[2] = 0x81;
+        response[3] = 0x80;
+        response[7] = 2;
+
+        let answers: &[u8] = &[
+            // www.example.com CNAME example.com
+            0xC0, 0x0C, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xC0, 0x10, //
+            // example.com A 93.184.216.34
+            0xC0, 0x10, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 93, 184, 216, 34,
+        ];
+        response[len..len + answers.len()].copy_from_slice(answers);
+
+        let msg = &response[..len + answers.len()];
+
+        assert_eq!(parse_response(0x1234, msg), Ok([93, 184, 216, 34]));
+        assert_eq!(parse_response(0x4321, msg), Err(DnsError::Malformed));
+        assert_eq!(
+            parse_response(0x1234, &msg[..msg.len() - 1]),
+            Err(DnsError::Malformed)
+        );
+    }
+}
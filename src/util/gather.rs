@@ -0,0 +1,42 @@
+// Iterates over the bytes of several slices in turn, as one ExactSizeIterator
+// so that they can go out as a single SendParam::Bytes.
+pub struct Gather<'a> {
+    bufs: &'a [&'a [u8]],
+    pos: usize,
+    remaining: usize,
+}
+
+impl<'a> Gather<'a> {
+    pub fn new(bufs: &'a [&'a [u8]]) -> Self {
+        Gather {
+            bufs,
+            pos: 0,
+            remaining: bufs.iter().map(|b| b.len()).sum(),
+        }
+    }
+}
+
+impl<'a> Iterator for Gather<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        while let Some((first, rest)) = self.bufs.split_first() {
+            if let Some(&b) = first.get(self.pos) {
+                self.pos += 1;
+                self.remaining -= 1;
+                return Some(b);
+            }
+
+            self.bufs = rest;
+            self.pos = 0;
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for Gather<'a> {}
@@ -1,3 +1,6 @@
+pub(crate) mod dns;
+pub mod gather;
 pub mod safe_spi;
 pub mod spi_ext;
 pub mod timeout_iter;
+pub mod timer;
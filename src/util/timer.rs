@@ -0,0 +1,70 @@
+use embedded_hal::timer::CountDown;
+
+// Measures the timeouts of the loops that poll the chip: waiting for the busy
+// pin and waiting for a response to start. Between polls the loops sleep with
+// the Delay; the Timer decides when they’ve waited long enough.
+pub trait Timer {
+    // Starts timing a wait of `ms` milliseconds.
+    fn start(&mut self, ms: u16);
+
+    // Called after each poll, which has included a sleep of `poll_ms`. True
+    // once the wait is over.
+    fn expired(&mut self, poll_ms: u16) -> bool;
+}
+
+// The default Timer. Assumes each poll takes only as long as its sleep, so
+// the real wait is somewhat longer than asked for, by however long the SPI
+// traffic took.
+#[derive(Debug, Default)]
+pub struct PollCounter {
+    remaining_ms: u16,
+}
+
+impl Timer for PollCounter {
+    fn start(&mut self, ms: u16) {
+        self.remaining_ms = ms;
+    }
+
+    fn expired(&mut self, poll_ms: u16) -> bool {
+        self.remaining_ms = self.remaining_ms.saturating_sub(poll_ms);
+        self.remaining_ms == 0
+    }
+}
+
+// Measures the waits with a hardware CountDown timer, so that they take as
+// long as asked for no matter how long each poll’s SPI traffic takes.
+// `to_time` converts milliseconds into the timer’s own Time type, e.g.
+// `|ms| (ms as u32).millis()`.
+pub struct CountDownTimer<C, F> {
+    timer: C,
+    to_time: F,
+}
+
+impl<C, F> CountDownTimer<C, F>
+where
+    C: CountDown,
+    F: FnMut(u16) -> C::Time,
+{
+    pub fn new(timer: C, to_time: F) -> Self {
+        CountDownTimer { timer, to_time }
+    }
+
+    pub fn free(self) -> C {
+        self.timer
+    }
+}
+
+impl<C, F> Timer for CountDownTimer<C, F>
+where
+    C: CountDown,
+    F: FnMut(u16) -> C::Time,
+{
+    fn start(&mut self, ms: u16) {
+        let time = (self.to_time)(ms);
+        self.timer.start(time);
+    }
+
+    fn expired(&mut self, _poll_ms: u16) -> bool {
+        self.timer.wait().is_ok()
+    }
+}